@@ -0,0 +1,95 @@
+A small Mandelbrot set drawn with fixed point numbers scaled by 8
+Real parts go from minus two to one half across 21 columns and imaginary parts
+from minus one to one down 17 rows
+Every point still bounded after 16 iterations is drawn as a star
+Signed numbers are kept as a sign cell and a magnitude cell so everything fits into 8 bit cells
+
+>>>>++++++++>++++++++++++++++<<<<+++++++++++++++++[->[-]>[-]<<<[->>>>>>>+>>>+<<<
+<<<<<<<]>>>>>>>>>>[-<<<<<<<<<<+>>>>>>>>>>]<<<<<<[->>>>+>>+<<<<<<]>>>>>>[-<<<<<<+
+>>>>>>]<<[-<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[[-]<+>]+<[->-<<<<->>>]>[-<<[-]+>
+>]<<<]<[-]<+>>>[<<<->>>-]<<+<[->-<<<<<<<[->>>+>>>>>+<<<<<<<<]>>>>>>>>[-<<<<<<<<+
+>>>>>>>>]<<<<[-<->>>>>+<<<<]>>>>[-<<<<+>>>>]<<]>[-<<<<<+>>[-<+>>>>>+<<<<]>>>>[-<
+<<<+>>>>]<<<<<<<<[->>>->>>>>+<<<<<<<<]>>>>>>>>[-<<<<<<<<+>>>>>>>>]<]++++++++++++
++++++++++[->[-]>[-]<<<[->>>>>+>>>+<<<<<<<<]>>>>>>>>[-<<<<<<<<+>>>>>>>>]<<<<<<<<<
+[->>>>>>>+>>+<<<<<<<<<]>>>>>>>>>[-<<<<<<<<<+>>>>>>>>>]<<[-<[->>>>+>+<<<<<]>>>>>[
+-<<<<<+>>>>>]<[[-]<+>]+<[->-<<<<->>>]>[-<<[-]+>>]<<<]<[-]<+>>>[<<<->>>-]<<+<[->-
+<<<<<[->>>+>>>+<<<<<<]>>>>>>[-<<<<<<+>>>>>>]<<<<<<<[->>>>->>>+<<<<<<<]>>>>>>>[-<
+<<<<<<+>>>>>>>]<<]>[-<<<+<<<[->>>>+>>>+<<<<<<<]>>>>>>>[-<<<<<<<+>>>>>>>]<<<<<<[-
+>>>->>>+<<<<<<]>>>>>>[-<<<<<<+>>>>>>]<]>>>+>+>++++++++++++++++<<[<<<[->>>>>>>>+>
+>>+<<<<<<<<<<<]>>>>>>>>>>>[-<<<<<<<<<<<+>>>>>>>>>>>]<<<<<<<<<<<<<<<<<[->>>>>>>>>
+>>>>>>+>>+<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>
+>>]<<[-<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[[-]<+>]+<[->-<<<<->>>]>[-<<[-]+>>]<<
+<]<[-]<+>>>[<<<->>>-]<<<[-<+>]<<<<<[->>>>>>+>>>+<<<<<<<<<]>>>>>>>>>[-<<<<<<<<<+>
+>>>>>>>>]<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>+>>+<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>
+>>[-<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>]<<[-<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<
+[[-]<+>]+<[->-<<<<->>>]>[-<<[-]+>>]<<<]<[-]<+>>>[<<<->>>-]<<<[-<+>]<[->+>+<<]>>[
+-<<+>>]<[[-]<<<<[-]>[-]>>>]<[-]<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[[-]<<<<<<<
+[->>>>>>>>>>>+>+<<<<<<<<<<<<]>>>>>>>>>>>>[-<<<<<<<<<<<<+>>>>>>>>>>>>]<[-<<<<<<<<
+<<<[->>>>>>>>>>+>>+<<<<<<<<<<<<]>>>>>>>>>>>>[-<<<<<<<<<<<<+>>>>>>>>>>>>]<]<[->+[
+->+>+<<]>>[-<<+>>]<-------->+<[[-]>-<]>[<<[-]<<<+>>>>>-]<<<]>[-]<<<<<<<<<[->>>>>
+>>>>+>+<<<<<<<<<<]>>>>>>>>>>[-<<<<<<<<<<+>>>>>>>>>>]<[-<<<<<<<<<[->>>>>>>>+>>+<<
+<<<<<<<<]>>>>>>>>>>[-<<<<<<<<<<+>>>>>>>>>>]<]<[->+[->+>+<<]>>[-<<+>>]<-------->+
+<[[-]>-<]>[<<[-]<<+>>>>-]<<<]>[-]<<<[->>>+>+<<<<]>>>>[-<<<<+>>>>]<<<[->>+>+<<<]>
+>>[-<<<+>>>]++++++++++++++++++++++++++++++++[->>+>>>+<<<<<]>>>>>[-<<<<<+>>>>>]<<
+<<<<[->>>>+>>+<<<<<<]>>>>>>[-<<<<<<+>>>>>>]<<[-<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>
+>]<[[-]<+>]+<[->-<<<<->>>]>[-<<[-]+>>]<<<]<[-]<+>>>[<<<->>>-]<<<<[-]<[-]+>>[-<<-
+<<<<<<<<<<<[->>>>>>>>>>>>>>+>+<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<+>
+>>>>>>>>>>>>>>]<[-<<<<<<<<<<<<[->>>>>>>>+>>>>>+<<<<<<<<<<<<<]>>>>>>>>>>>>>[-<<<<
+<<<<<<<<<+>>>>>>>>>>>>>]<]<<<<[->>>>+[->+>+<<]>>[-<<+>>]<-------->+<[[-]>-<]>[<<
+[-]<<+>>>>-]<<<<<<]>>>>[-]<<[->>>+>+<<<<]>>>>[-<<<<+>>>>]<<<<[->>>+>+<<<<]>>>>[-
+<<<<+>>>>]<<<<[-]<<<<<<<<<<<<<[->>>>>>>>>>>>>>>+>>+<<<<<<<<<<<<<<<<<]>>>>>>>>>>>
+>>>>>>[-<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<[->>>>>>>>>>>>>->>+<<
+<<<<<<<<<<<<<]>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>]<<[->>>+>+<<<<]>>
+>>[-<<<<+>>>>]<[[-]<+>]<<<[-]>>[-<<+>>]<<<<<<<<[->>>>>>>>>>+>>>+<<<<<<<<<<<<<]>>
+>>>>>>>>>>>[-<<<<<<<<<<<<<+>>>>>>>>>>>>>]<<<<<<<<<<<<[->>>>>>>>>>+>>+<<<<<<<<<<<
+<]>>>>>>>>>>>>[-<<<<<<<<<<<<+>>>>>>>>>>>>]<<[-<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>
+]<[[-]<+>]+<[->-<<<<->>>]>[-<<[-]+>>]<<<]<[-]<+>>>[<<<->>>-]<<+<[->-<<<<<<<<<<[-
+>>>>>>>>+>>>+<<<<<<<<<<<]>>>>>>>>>>>[-<<<<<<<<<<<+>>>>>>>>>>>]<<<<<<<<<<[->>>>>>
+>->>>+<<<<<<<<<<]>>>>>>>>>>[-<<<<<<<<<<+>>>>>>>>>>]<<]>[-<<<<<<+<<<[->>>>>>>+>>>
++<<<<<<<<<<]>>>>>>>>>>[-<<<<<<<<<<+>>>>>>>>>>]<<<<<<<<<<<[->>>>>>>>->>>+<<<<<<<<
+<<<]>>>>>>>>>>>[-<<<<<<<<<<<+>>>>>>>>>>>]<]<<<<<<<<<<<<<<<<<<<[-]>[-]>>>>>>>>>>>
+>[->>>>>+>+<<<<<<]>>>>>>[-<<<<<<+>>>>>>]<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>
+>>>->+<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<+>>>>>>>
+>>>>>>>>>>>>>>]<[->>+>+<<<]>>>[-<<<+>>>]<[[-]<+>]<<[-]+>[-<-<[->>>>+>>>+<<<<<<<]
+>>>>>>>[-<<<<<<<+>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>+>>+<
+<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<+>>
+>>>>>>>>>>>>>>>>>>>>>>>]<<[-<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[[-]<+>]+<[->-<<
+<<->>>]>[-<<[-]+>>]<<<]<[-]<+>>>[<<<->>>-]<<+<[->-<<<<<<<<[-<<<<<<<<<<<<<+>>>>>>
+>>>>>>>>>>>>>>>>+<<<<<<<<<]>>>>>>>>>[-<<<<<<<<<+>>>>>>>>>]<<<<<[-<<<<<<<<<<<<<<<
+<+>>>>>>>>>>>>>>>>>>>>>+<<<<<]>>>>>[-<<<<<+>>>>>]<<<<<<<<<<<<<<<<<<<<<<<[->>->>>
+>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<
+<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>>>>>]<<]>[-<<<<<<<<<<<<<<<<<<<<<<<[->>+>>>>>>>>>>
+>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<
+<<<<<<<<+>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<[->>+>>>>>>>>>>>>>>>>>>
+>>>+<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<+>>>
+>>>>>>>>>>>>>>>>>>>>]<<<<<[-<<<<<<<<<<<<<<<<->>>>>>>>>>>>>>>>>>>>>+<<<<<]>>>>>[-
+<<<<<+>>>>>]<]<<]<[-<<<<<[-<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>>+<<<<<<<]>>>>>>>[-<
+<<<<<<+>>>>>>>]<<<[-<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>+<<<]>>>[-<<<+>>>]<<<<<<
+<<<<<<<<<<<<<<<[->>+>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>
+>>>[-<<<<<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>>>]<<]<<<<<<<<<<<<<<<<[-]>[-]>>>>>
+>>>>>>>[->>>+>+<<<<]>>>>[-<<<<+>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>
+>>>>>>>>>>>->+<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<
+<<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>>>>>>>>>]<[->>+>+<<<]>>>[-<<<+>>>]<[[-]<+>
+]<<[-]+>[-<-<<[->>>>>+>>>+<<<<<<<<]>>>>>>>>[-<<<<<<<<+>>>>>>>>]<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>+>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>]<<[-<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[[-]<+>]+<[->-<<<<->>>
+]>[-<<[-]+>>]<<<]<[-]<+>>>[<<<->>>-]<<+<[->-<<<<<<[-<<<<<<<<<<<<<+>>>>>>>>>>>>>>
+>>>>>>+<<<<<<<]>>>>>>>[-<<<<<<<+>>>>>>>]<<<<<<[-<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>
+>+<<<<<<]>>>>>>[-<<<<<<+>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>->>>>>>>
+>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<]>[-<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<[->>>>>>>>>>+>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>+>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
++>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<[-<<<<<<<<<<<<<->>>>>>>>>>>>>>>>>>>+<<<<<<]
+>>>>>>[-<<<<<<+>>>>>>]<]<<]<[-<<<[-<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>+<<<<<]>>>>>[
+-<<<<<+>>>>>]<<<<[-<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>+<<<<]>>>>[-<<<<+>>>>]<<<<<<<<
+<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>+>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>
+>>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>>>>>>>
+>>]<<]<<<<<[-]>>>>[-]<<[-]>[-]<<<<<<<<<<-[->>>>>>>>>+>+<<<<<<<<<<]>>>>>>>>>>[-<<
+<<<<<<<<+>>>>>>>>>>]<[[-]<<+>>]+<<[->>-<<]>>[-<<<<<<<<<<<[-]>>>>>>>>>>>]<]<<[-<<
+<<<<<<[-]>[-]>>>>>>>]<<<[-]>[-]<<]<<<<]>>>++++++++++++++++++++++++++++++++<<[->>
+>+>+<<<<]>>>>[-<<<<+>>>>]<[[-]<++++++++++>]<.[-]<<<<<<<[-]>[-]>[-]>[-]>>[-]>[-]<
+<<<<<<<<<+>]<[-]>>>>++++++++++.[-]<<[-]>[-]<<<<<<<<<+>]
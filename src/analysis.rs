@@ -0,0 +1,147 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use std::fmt::{Display, Formatter};
+
+
+
+
+/// Something in a program that is almost certainly a mistake, though running it is still well defined.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+    /// A loop that never ends once it is entered.
+    InfiniteLoop { line: u32, char: u32 },
+    /// Code after a loop that is certainly entered and never ends, so it never runs.
+    UnreachableCode { line: u32, char: u32 },
+}
+impl Warning {
+    /// The line and char the warning is about.
+    pub fn position(&self) -> (u32, u32) {
+        match *self {
+            Self::InfiniteLoop { line, char } => (line, char),
+            Self::UnreachableCode { line, char } => (line, char),
+        }
+    }
+}
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InfiniteLoop { line, char } => {
+                write!(f, "Loop on line {}, char {} never changes the cell it checks, so it never ends once entered", line, char)
+            }
+            Self::UnreachableCode { line, char } => {
+                write!(f, "Code from line {}, char {} on never runs, since the loop before it never ends", line, char)
+            }
+        }
+    }
+}
+
+
+/// Looks through the whole program for loops that never end once entered, and for code after them that never runs.
+/// Only warns about what it is certain of, so plenty of hanging programs get no warning at all.
+/// Loops that are never entered, like initial comment loops and loops right after another loop, are skipped.
+/// Assumes the tape starts out as zero, and that the pointer doesn't wrap around it,
+/// which could lead it back to the checked cell.
+pub fn find_warnings(program: &InstructionNode) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    if let NodeType::Program(children) = &program.node_type {
+        // Loop bodies still to look through, on a stack instead of recursing, so deep nesting can't overflow it.
+        let mut pending = vec![(&children[..], true)];
+        while let Some((nodes, starts_zero)) = pending.pop() {
+            find_in(nodes, starts_zero, &mut warnings, &mut pending);
+        }
+    }
+    warnings.sort_by_key(|warning| warning.position());
+    warnings
+}
+
+/// Looks through the sibling nodes, adding the bodies of the loops that may be entered to the pending ones.
+fn find_in<'a>(nodes: &'a [InstructionNode], starts_zero: bool, warnings: &mut Vec<Warning>, pending: &mut Vec<(&'a [InstructionNode], bool)>) {
+    let reachable = first_unreachable(nodes, starts_zero).unwrap_or(nodes.len());
+
+    let mut cell_is_zero = starts_zero;
+    for node in &nodes[..reachable] {
+        if let NodeType::Loop(children) = &node.node_type {
+            if !cell_is_zero {
+                if never_terminates(children) {
+                    warnings.push(Warning::InfiniteLoop {
+                        line: node.line,
+                        char: node.char,
+                    });
+                }
+                pending.push((children, false));
+            }
+        }
+
+        cell_is_zero = matches!(node.node_type, NodeType::Loop(_));
+    }
+
+    if let Some(node) = nodes.get(reachable) {
+        warnings.push(Warning::UnreachableCode {
+            line: node.line,
+            char: node.char,
+        });
+    }
+}
+
+
+/// Whether a loop with this body certainly runs forever once entered:
+/// the body does no I/O, ends where it started, never moves left of its start
+/// (which could fail with a pointer underflow instead), and never touches the cell the loop checks.
+/// Bodies with nested loops or anything else too complicated to be sure about don't count.
+fn never_terminates(body: &[InstructionNode]) -> bool {
+    let mut offset = 0isize;
+
+    for node in body {
+        match &node.node_type {
+            NodeType::Next(amount) => offset += *amount as isize,
+            NodeType::Previous(amount) => {
+                offset -= *amount as isize;
+                if offset < 0 {
+                    return false;
+                }
+            }
+            NodeType::Increment(_) | NodeType::Decrement(_) | NodeType::SetCell(_) => {
+                if offset == 0 {
+                    return false;
+                }
+            }
+            NodeType::AddAtOffset { offset: target, .. } | NodeType::SetAtOffset { offset: target, .. } => {
+                if offset + target <= 0 {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+
+    offset == 0
+}
+
+/// The index of the first of the sibling nodes that never runs,
+/// because it comes after a loop that never terminates and is certainly entered,
+/// since the current cell is known not to be zero at every cell width when reaching it.
+/// Knowing the current cell relies on it starting at zero at the start of the program.
+pub(crate) fn first_unreachable(nodes: &[InstructionNode], starts_zero: bool) -> Option<usize> {
+    // The value of the current cell, if known, with wrapping arithmetic like the values of SetCell.
+    let mut known = if starts_zero { Some(0usize) } else { None };
+
+    for (i, node) in nodes.iter().enumerate() {
+        if let NodeType::Loop(children) = &node.node_type {
+            let is_entered = matches!(known, Some(value) if value as u8 != 0);
+            if is_entered && never_terminates(children) {
+                return Some(i + 1).filter(|&next| next < nodes.len());
+            }
+        }
+
+        known = match &node.node_type {
+            NodeType::Loop(_) | NodeType::ScanRight(_) | NodeType::ScanLeft(_) => Some(0),
+            NodeType::Increment(amount) => known.map(|value| value.wrapping_add(*amount)),
+            NodeType::Decrement(amount) => known.map(|value| value.wrapping_sub(*amount)),
+            NodeType::SetCell(value) => Some(*value),
+            NodeType::Output | NodeType::Debug => known,
+            NodeType::AddAtOffset { offset, .. } | NodeType::SetAtOffset { offset, .. } if *offset != 0 => known,
+            _ => None,
+        };
+    }
+
+    None
+}
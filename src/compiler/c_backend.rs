@@ -0,0 +1,210 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::compiler::{CodegenOptions, CellWidth, uses_debug_commands};
+use crate::interpreter::{EofPolicy, DEBUG_RADIUS};
+use std::fmt::Write;
+
+
+
+
+/// Translates the program into a standalone C source file,
+/// behaving the same as the program compiled through LLVM:
+/// cells wrap around at the cell width, the tape starts with `tape_size` cells and doubles whenever the pointer runs off its end,
+/// moving left of the first cell exits with an error, and EOF is handled according to the policy.
+/// Only the cell width, EOF policy, tape size, initial cell value, exit code and output buffering apply;
+/// the options choosing the target, entry point and debug info are left to the C compiler.
+pub fn compile_to_c(node: &InstructionNode, options: &CodegenOptions) -> String {
+    let mut out = String::new();
+    write_prelude(&mut out, options);
+    if uses_debug_commands(node) {
+        write_debug_dump(&mut out, options);
+    }
+
+    out.push_str("\nint main(void) {\n");
+    out.push_str("    len = TAPE_SIZE;\n");
+    out.push_str("    mem = alloc_cells(len);\n");
+    out.push_str("    if (!mem) fail(\"\\nError: Failed to allocate cell array\\n\");\n\n");
+
+    match &node.node_type {
+        NodeType::Program(children) => write_nodes(&mut out, children, 1, options),
+        _ => write_node(&mut out, node, 1, options),
+    }
+
+    if options.exit_from_cell {
+        out.push_str("\n    int code = *cell(p);\n");
+        out.push_str("    free(mem);\n");
+        out.push_str("    return code;\n");
+    }
+    else {
+        out.push_str("\n    free(mem);\n");
+        out.push_str("    return 0;\n");
+    }
+    out.push_str("}\n");
+    out
+}
+
+
+fn write_prelude(out: &mut String, options: &CodegenOptions) {
+    let cell_type = match options.cell_width {
+        CellWidth::Bits8 => "uint8_t",
+        CellWidth::Bits16 => "uint16_t",
+        CellWidth::Bits32 => "uint32_t",
+    };
+
+    out.push_str("#include <stdint.h>\n");
+    out.push_str("#include <stdio.h>\n");
+    out.push_str("#include <stdlib.h>\n");
+    out.push_str("#include <string.h>\n\n");
+    writeln!(out, "#define TAPE_SIZE {}", options.tape_size).unwrap();
+    writeln!(out, "typedef {} cell_t;\n", cell_type).unwrap();
+    out.push_str("static cell_t *mem;\n");
+    out.push_str("static size_t len;\n");
+    out.push_str("static size_t p;\n\n");
+
+    out.push_str("static void fail(const char *message) {\n");
+    out.push_str("    fputs(message, stderr);\n");
+    out.push_str("    fflush(stdout);\n");
+    out.push_str("    free(mem);\n");
+    out.push_str("    exit(0);\n");
+    out.push_str("}\n\n");
+
+    out.push_str("/* Allocates the number of cells, with every byte set to the initial value. */\n");
+    out.push_str("static inline cell_t *alloc_cells(size_t count) {\n");
+    if options.init_cell == 0 {
+        out.push_str("    return calloc(count, sizeof(cell_t));\n");
+    }
+    else {
+        out.push_str("    cell_t *cells = malloc(count * sizeof(cell_t));\n");
+        writeln!(out, "    if (cells) memset(cells, {}, count * sizeof(cell_t));", options.init_cell).unwrap();
+        out.push_str("    return cells;\n");
+    }
+    out.push_str("}\n\n");
+
+    out.push_str("/* Grows the tape to include the index, at least doubling it, with the new cells initialized. */\n");
+    out.push_str("static inline void resize(size_t index) {\n");
+    out.push_str("    if (index < len) return;\n");
+    out.push_str("    size_t new_len = len * 2 > index + 1 ? len * 2 : index + 1;\n");
+    out.push_str("    cell_t *new_mem = alloc_cells(new_len);\n");
+    out.push_str("    if (!new_mem) fail(\"\\nError: Failed to resize cell array\\n\");\n");
+    out.push_str("    memcpy(new_mem, mem, len * sizeof(cell_t));\n");
+    out.push_str("    free(mem);\n");
+    out.push_str("    mem = new_mem;\n");
+    out.push_str("    len = new_len;\n");
+    out.push_str("}\n\n");
+
+    out.push_str("/* The cell at the index, growing the tape to include it first.\n");
+    out.push_str("   The pointer is only valid until the next call, which might move the tape. */\n");
+    out.push_str("static inline cell_t *cell(size_t index) {\n");
+    out.push_str("    resize(index);\n");
+    out.push_str("    return &mem[index];\n");
+    out.push_str("}\n\n");
+
+    out.push_str("/* The index the amount of cells left of the pointer, which has to be on the tape. */\n");
+    out.push_str("static inline size_t left(size_t amount) {\n");
+    out.push_str("    if (amount > p) fail(\"\\nError: Tried to decrement index, resulting underflow\\n\");\n");
+    out.push_str("    return p - amount;\n");
+    out.push_str("}\n");
+}
+
+fn write_debug_dump(out: &mut String, options: &CodegenOptions) {
+    out.push_str("\nstatic void debug_dump(int line, int ch) {\n");
+    writeln!(out, "    size_t first = p >= {0} ? p - {0} : 0;", DEBUG_RADIUS).unwrap();
+    out.push_str("    fprintf(stderr, \"\\nDebug on line %d, char %d: pointer %llu, cells from %llu:\", line, ch, (unsigned long long) p, (unsigned long long) first);\n");
+    writeln!(out, "    for (size_t i = first; i <= p + {}; i++) {{", DEBUG_RADIUS).unwrap();
+    writeln!(out, "        unsigned long long value = i < len ? mem[i] : {}u;", options.cell_width.fill(options.init_cell)).unwrap();
+    out.push_str("        fprintf(stderr, i == p ? \" [%llu]\" : \" %llu\", value);\n");
+    out.push_str("    }\n");
+    out.push_str("    fputc('\\n', stderr);\n");
+    out.push_str("}\n");
+}
+
+
+fn write_nodes(out: &mut String, nodes: &[InstructionNode], depth: usize, options: &CodegenOptions) {
+    for node in nodes {
+        write_node(out, node, depth, options);
+    }
+}
+
+fn write_node(out: &mut String, node: &InstructionNode, depth: usize, options: &CodegenOptions) {
+    let indent = "    ".repeat(depth);
+    let width = options.cell_width;
+
+    match &node.node_type {
+        NodeType::Program(children) => write_nodes(out, children, depth, options),
+        NodeType::Next(amount) => writeln!(out, "{}p += {};", indent, amount).unwrap(),
+        NodeType::Previous(amount) => writeln!(out, "{}p = left({});", indent, amount).unwrap(),
+        NodeType::Increment(amount) => writeln!(out, "{}*cell(p) += {}u;", indent, width.wrap(*amount)).unwrap(),
+        NodeType::Decrement(amount) => writeln!(out, "{}*cell(p) -= {}u;", indent, width.wrap(*amount)).unwrap(),
+        NodeType::SetCell(value) => writeln!(out, "{}*cell(p) = {}u;", indent, width.wrap(*value)).unwrap(),
+        NodeType::Output => {
+            writeln!(out, "{}putchar((unsigned char) *cell(p));", indent).unwrap();
+            if !options.buffered_output {
+                writeln!(out, "{}fflush(stdout);", indent).unwrap();
+            }
+        }
+        NodeType::Input => {
+            let on_eof = match options.eof_policy {
+                EofPolicy::Unchanged => String::new(),
+                EofPolicy::Zero => " else *cell(p) = 0;".to_owned(),
+                EofPolicy::NegativeOne => " else *cell(p) = (cell_t) -1;".to_owned(),
+            };
+            writeln!(out, "{}{{", indent).unwrap();
+            writeln!(out, "{}    int c = getchar();", indent).unwrap();
+            writeln!(out, "{}    if (c != EOF) *cell(p) = (cell_t) c;{}", indent, on_eof).unwrap();
+            writeln!(out, "{}}}", indent).unwrap();
+        }
+        NodeType::Loop(children) => {
+            writeln!(out, "{}while (*cell(p)) {{", indent).unwrap();
+            write_nodes(out, children, depth + 1, options);
+            writeln!(out, "{}}}", indent).unwrap();
+        }
+        NodeType::Debug => writeln!(out, "{}debug_dump({}, {});", indent, node.line, node.char).unwrap(),
+        NodeType::MultiplyAdd { targets } => {
+            // The value is read first, since growing the tape for a target moves the cells.
+            writeln!(out, "{}{{", indent).unwrap();
+            writeln!(out, "{}    cell_t value = *cell(p);", indent).unwrap();
+            writeln!(out, "{}    if (value) {{", indent).unwrap();
+            for &(offset, factor) in targets {
+                let factor = width.wrap(factor as i64 as usize);
+                writeln!(out, "{}        *cell({}) += (cell_t) ((uint32_t) value * {}u);", indent, offset_index(offset), factor).unwrap();
+            }
+            writeln!(out, "{}    }}", indent).unwrap();
+            writeln!(out, "{}}}", indent).unwrap();
+        }
+        NodeType::MoveAdd { offset } => {
+            writeln!(out, "{}{{", indent).unwrap();
+            writeln!(out, "{}    cell_t value = *cell(p);", indent).unwrap();
+            writeln!(out, "{}    if (value) *cell({}) += value;", indent, offset_index(*offset)).unwrap();
+            writeln!(out, "{}}}", indent).unwrap();
+        }
+        NodeType::ScanRight(1) if width == CellWidth::Bits8 && options.init_cell == 0 => {
+            // Cells past the end of the tape are all zero, so stop there if memchr finds nothing.
+            writeln!(out, "{}{{", indent).unwrap();
+            writeln!(out, "{}    resize(p);", indent).unwrap();
+            writeln!(out, "{}    cell_t *zero = memchr(mem + p, 0, len - p);", indent).unwrap();
+            writeln!(out, "{}    p = zero ? (size_t) (zero - mem) : len;", indent).unwrap();
+            writeln!(out, "{}}}", indent).unwrap();
+        }
+        NodeType::ScanRight(stride) => writeln!(out, "{}while (*cell(p)) p += {};", indent, stride).unwrap(),
+        NodeType::ScanLeft(stride) => writeln!(out, "{}while (*cell(p)) p = left({});", indent, stride).unwrap(),
+        NodeType::AddAtOffset { offset, amount } => {
+            let amount = width.wrap(*amount as i64 as usize);
+            writeln!(out, "{}*cell({}) += {}u;", indent, offset_index(*offset), amount).unwrap();
+        }
+        NodeType::SetAtOffset { offset, value } => {
+            writeln!(out, "{}*cell({}) = {}u;", indent, offset_index(*offset), width.wrap(*value)).unwrap();
+        }
+    }
+}
+
+/// The C expression for the index of the cell at the offset from the pointer.
+fn offset_index(offset: isize) -> String {
+    if offset < 0 {
+        format!("left({})", offset.wrapping_neg() as usize)
+    }
+    else if offset > 0 {
+        format!("p + {}", offset)
+    }
+    else {
+        "p".to_owned()
+    }
+}
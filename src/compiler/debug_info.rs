@@ -0,0 +1,89 @@
+use crate::front_end::parser::InstructionNode;
+use inkwell::context::Context;
+use inkwell::builder::Builder;
+use inkwell::module::{Module, FlagBehavior};
+use inkwell::values::FunctionValue;
+use inkwell::debug_info::{
+    DebugInfoBuilder, DICompileUnit, DISubprogram, DIFlags, DIFlagsConstants,
+    DWARFSourceLanguage, DWARFEmissionKind, AsDIScope,
+};
+use std::cell::Cell;
+
+
+
+
+/// DWARF line information mapping the generated entry function back to the brainfuck source.
+/// Only the entry function is described; the helper functions have no source of their own.
+pub struct DebugInfo<'ctx> {
+    builder: DebugInfoBuilder<'ctx>,
+    unit: DICompileUnit<'ctx>,
+    subprogram: Cell<Option<DISubprogram<'ctx>>>,
+}
+impl<'ctx> DebugInfo<'ctx> {
+    pub fn new(context: &'ctx Context, module: &Module<'ctx>, file_name: &str) -> DebugInfo<'ctx> {
+        let version = context.i32_type().const_int(3, false);
+        module.add_basic_value_flag("Debug Info Version", FlagBehavior::Warning, version);
+
+        let (builder, unit) = module.create_debug_info_builder(
+            true,
+            DWARFSourceLanguage::C,
+            file_name,
+            ".",
+            "rustfuck",
+            false,
+            "",
+            0,
+            "",
+            DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+        );
+
+        DebugInfo {
+            builder,
+            unit,
+            subprogram: Cell::new(None),
+        }
+    }
+
+    /// Describes the function the program body is generated into, so locations can be attached to it.
+    pub fn attach_entry(&self, function: FunctionValue<'ctx>, name: &str) {
+        let file = self.unit.get_file();
+        let fn_type = self.builder.create_subroutine_type(file, None, &[], DIFlags::PUBLIC);
+        let subprogram = self.builder.create_function(
+            self.unit.as_debug_info_scope(),
+            name,
+            None,
+            file,
+            1,
+            fn_type,
+            false,
+            true,
+            1,
+            DIFlags::PUBLIC,
+            false,
+        );
+
+        function.set_subprogram(subprogram);
+        self.subprogram.set(Some(subprogram));
+    }
+
+    /// Attributes all following instructions to the source position of the node.
+    pub fn set_location(&self, context: &'ctx Context, builder: &Builder<'ctx>, node: &InstructionNode) {
+        if let Some(subprogram) = self.subprogram.get() {
+            let location = self.builder.create_debug_location(
+                context,
+                node.line,
+                node.char,
+                subprogram.as_debug_info_scope(),
+                None,
+            );
+            builder.set_current_debug_location(context, location);
+        }
+    }
+
+    pub fn finalize(&self) {
+        self.builder.finalize();
+    }
+}
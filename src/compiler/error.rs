@@ -0,0 +1,50 @@
+use crate::front_end::parser::ParsingError;
+use std::fmt::{Display, Formatter};
+use std::io;
+
+
+
+
+/// Everything that can go wrong between reading a brainfuck file and linking the executable.
+#[derive(Debug)]
+pub enum CompileError {
+    ReadSource(io::Error),
+    Parse(ParsingError),
+    CreateDir(io::Error),
+    WriteBitcode(io::Error),
+    WriteObject(io::Error),
+    WriteHelper(io::Error),
+    WriteIr(io::Error),
+    WriteC(io::Error),
+    WriteCfg(io::Error),
+    RemoveIntermediate(io::Error),
+    Codegen(String),
+    InvalidOptLevel(u32),
+    Llc(String),
+    Ld(String),
+}
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReadSource(err) => write!(f, "Failed to read input file: {}", err),
+            Self::Parse(err) => write!(f, "Failed to parse brainfuck program: {}", err),
+            Self::CreateDir(err) => write!(f, "Failed to create directory: {}", err),
+            Self::WriteBitcode(err) => write!(f, "Failed to write bitcode file: {}", err),
+            Self::WriteObject(err) => write!(f, "Failed to write object file: {}", err),
+            Self::WriteHelper(err) => write!(f, "Failed to write helper obj file: {}", err),
+            Self::WriteIr(err) => write!(f, "Failed to write IR file: {}", err),
+            Self::WriteC(err) => write!(f, "Failed to write C file: {}", err),
+            Self::WriteCfg(err) => write!(f, "Failed to write CFG file: {}", err),
+            Self::RemoveIntermediate(err) => write!(f, "Failed to remove intermediate file: {}", err),
+            Self::Codegen(err) => write!(f, "Failed to compile object file: {}", err),
+            Self::InvalidOptLevel(level) => write!(f, "Invalid optimization level: {}", level),
+            Self::Llc(err) => write!(f, "{}", err),
+            Self::Ld(err) => write!(f, "{}", err),
+        }
+    }
+}
+impl From<ParsingError> for CompileError {
+    fn from(err: ParsingError) -> Self {
+        CompileError::Parse(err)
+    }
+}
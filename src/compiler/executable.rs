@@ -0,0 +1,385 @@
+use crate::front_end::parser::InstructionNode;
+use crate::timing::PhaseTimes;
+use crate::compiler::{compile_to_ir, compile_to_object, CodegenOptions, CompileError, TargetOs, EntryPoint};
+use std::path::{PathBuf, Path};
+use std::process::Command;
+use std::ffi::{OsString, OsStr};
+
+
+
+
+/// Settings for turning a program into an executable,
+/// independent of the command line they usually come from.
+#[derive(Clone, Debug)]
+pub struct CompileOptions {
+    /// Where to write the executable, or None for the current directory.
+    pub output_path: Option<PathBuf>,
+    /// The name of the executable if the output path doesn't give one, usually the stem of the source file.
+    pub program_stem: OsString,
+    /// The name of the compiled module, which debug information refers to as the source file.
+    pub module_name: String,
+    /// Directory to store the bitcode, object and helper files in.
+    pub int_dir: PathBuf,
+    /// LLVM optimization level, any of 0, 1, 2, 3.
+    pub opt_level: u32,
+    /// Write bitcode and compile it with an external llc, instead of emitting the object file directly.
+    pub use_llc: bool,
+    /// The llc to run instead of the first one on the PATH.
+    /// It has to be from the same major LLVM version as the bitcode, see LLVM_MAJOR_VERSION.
+    pub llc: Option<PathBuf>,
+    /// The linker to run instead of the default one for the target, like ld.lld or mold in place of ld.
+    pub linker: Option<PathBuf>,
+    /// Extra arguments passed to the linker after the default ones.
+    pub linker_args: Vec<String>,
+    /// Link against the static C library, without a dynamic linker.
+    /// With glibc, this needs the main entry point, since its static library has to be started by the C runtime.
+    pub static_link: bool,
+    /// Delete the intermediate files written for this program once it has been linked.
+    /// By default they are kept, for looking into how the executable came about.
+    pub clean_int: bool,
+    /// The target, cell width, tape size and everything else about the generated code.
+    pub codegen: CodegenOptions,
+}
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            output_path: None,
+            program_stem: OsString::from("a"),
+            module_name: String::from("stdin"),
+            int_dir: PathBuf::from("./int/"),
+            opt_level: 2,
+            use_llc: false,
+            llc: None,
+            linker: None,
+            linker_args: Vec::new(),
+            static_link: false,
+            clean_int: false,
+            codegen: CodegenOptions::default(),
+        }
+    }
+}
+
+
+/// Compiles the program to an object file in the intermediate directory
+/// and links it into an executable for the target.
+/// With the library entry point, the object file itself is the result, which is copied to the output path with an .o extension.
+pub fn compile_to_executable(program: &InstructionNode, options: &CompileOptions) -> Result<(), CompileError> {
+    compile_to_executable_timed(program, options, &mut PhaseTimes::new())
+}
+
+/// Like compile_to_executable, but records how long code generation, llc and linking took.
+pub fn compile_to_executable_timed(program: &InstructionNode, options: &CompileOptions, times: &mut PhaseTimes) -> Result<(), CompileError> {
+    let (mut out_path, out_stem) = executable_path(options);
+    let int_path = options.int_dir.clone();
+    let mut bc_path = int_path.clone();
+    bc_path.push(format!("int_{}.bc", out_stem.to_str().unwrap()));
+    let mut obj_path = int_path.clone();
+    obj_path.push(format!("int_{}.o", out_stem.to_str().unwrap()));
+    // Named after the program like the other intermediate files,
+    // so builds of different programs into the same directory don't overwrite each other's helper.
+    let mut flush_path = int_path;
+    flush_path.push(format!("int_{}_flush_stdout_helper.o", out_stem.to_str().unwrap()));
+
+
+    match options.codegen.target_os {
+        TargetOs::Windows => {
+            out_path.set_extension("exe");
+        }
+        TargetOs::Wasm32 => {
+            out_path.set_extension("wasm");
+        }
+        TargetOs::Linux => {
+            if options.codegen.entry_point() == EntryPoint::Library {
+                out_path.set_extension("o");
+            }
+        }
+    }
+
+    std::fs::create_dir_all(bc_path.parent().unwrap()).map_err(CompileError::CreateDir)?;
+    std::fs::create_dir_all(out_path.parent().unwrap()).map_err(CompileError::CreateDir)?;
+
+
+
+    // Only the files written here are removed with clean_int, since other builds may share the directory.
+    let mut intermediates = vec![obj_path.clone()];
+
+    if options.use_llc {
+        let bc_module = times.time("codegen", || compile_to_ir(program, &options.module_name, &options.codegen));
+
+        std::fs::write(&bc_path, bc_module.as_slice()).map_err(CompileError::WriteBitcode)?;
+        intermediates.push(bc_path.clone());
+
+        times.time("llc", || invoke_llc(&bc_path, &obj_path, options))?;
+    }
+    else {
+        let object = times.time("codegen", || compile_to_object(program, &options.module_name, &options.codegen, options.opt_level))
+            .map_err(CompileError::Codegen)?;
+
+        std::fs::write(&obj_path, object).map_err(CompileError::WriteObject)?;
+    }
+
+    times.time("link", || match (options.codegen.target_os, options.codegen.entry_point()) {
+        (TargetOs::Linux, EntryPoint::Start) if options.codegen.pie() => invoke_ld(&obj_path, None, &out_path, options),
+        (TargetOs::Linux, EntryPoint::Start) => {
+            write_flush_helper(&flush_path)?;
+            intermediates.push(flush_path.clone());
+            invoke_ld(&obj_path, Some(&flush_path), &out_path, options)
+        }
+        (TargetOs::Linux, EntryPoint::Main) => invoke_cc(&obj_path, &out_path, options),
+        (TargetOs::Linux, EntryPoint::Library) => std::fs::copy(&obj_path, &out_path)
+            .map(|_| ())
+            .map_err(CompileError::WriteObject),
+        (TargetOs::Windows, _) => invoke_lld_link(&obj_path, &out_path, options),
+        (TargetOs::Wasm32, _) => invoke_wasm_ld(&obj_path, &out_path, options),
+    })?;
+
+    if options.clean_int {
+        for path in intermediates {
+            std::fs::remove_file(path).map_err(CompileError::RemoveIntermediate)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Where to write the executable, and the stem naming it and its intermediate files.
+/// An output path that is an existing directory or ends with a separator gets an executable named after the program inside it,
+/// any other output path is the executable itself.
+fn executable_path(options: &CompileOptions) -> (PathBuf, OsString) {
+    let is_existing_dir = matches!(&options.output_path, Some(path) if path.is_dir());
+    resolve_executable_path(options.output_path.as_deref(), &options.program_stem, is_existing_dir)
+}
+/// Does the work of executable_path without touching the file system,
+/// with whether the output path is an existing directory looked up by the caller.
+fn resolve_executable_path(output_path: Option<&Path>, program_stem: &OsStr, is_existing_dir: bool) -> (PathBuf, OsString) {
+    match output_path {
+        Some(path) if !is_existing_dir && !has_trailing_separator(path) => {
+            let stem = path.file_stem().unwrap_or(program_stem);
+            (path.to_path_buf(), stem.to_os_string())
+        }
+        path => {
+            let mut out_path = path.map_or_else(|| PathBuf::from("./"), Path::to_path_buf);
+            out_path.push(program_stem);
+            (out_path, program_stem.to_os_string())
+        }
+    }
+}
+fn has_trailing_separator(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(std::path::is_separator)
+}
+
+/// The major version of the LLVM the bitcode is generated with, which llc has to match to read it.
+pub const LLVM_MAJOR_VERSION: u32 = 7;
+
+fn invoke_llc(bc_path: &Path, obj_path: &Path, options: &CompileOptions) -> Result<(), CompileError> {
+    if options.opt_level > 3 {
+        return Err(CompileError::InvalidOptLevel(options.opt_level));
+    }
+
+    let llc = options.llc.clone().unwrap_or_else(|| PathBuf::from("llc"));
+    match Command::new(&llc)
+        .arg("-o").arg(obj_path)
+        .arg(bc_path.to_str().unwrap())
+        .arg("-filetype=obj")
+        .arg(format!("-O{}", options.opt_level))
+        .args(if options.codegen.pie() { &["-relocation-model=pic"][..] } else { &[] })
+        .output() {
+        Ok(output) => {
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let mut msg = format!("Llc returned failure exit status:\n {} ", stderr);
+                if is_bitcode_mismatch(&stderr) {
+                    msg.push_str(&format!(
+                        "\nThe bitcode was generated by LLVM {}, which {} might not match. Use --llc to choose the llc of LLVM {}.",
+                        LLVM_MAJOR_VERSION, llc.display(), LLVM_MAJOR_VERSION,
+                    ));
+                }
+                return Err(CompileError::Llc(msg));
+            }
+        },
+        Err(err) => {
+            return Err(CompileError::Llc(format!("Failed to invoke {}: {}", llc.display(), err)));
+        }
+    };
+
+    Ok(())
+}
+/// Whether llc failed because it couldn't read the bitcode, which usually means it is from another LLVM version.
+fn is_bitcode_mismatch(stderr: &str) -> bool {
+    ["Invalid bitcode", "Unknown attribute kind", "Invalid record", "Invalid value", "bitcode version"]
+        .iter()
+        .any(|symptom| stderr.contains(symptom))
+}
+
+
+const FLUSH_OBJ: &[u8] = include_bytes!("../helper/flush_stdout.o");
+fn write_flush_helper(path: &Path) -> Result<(), CompileError> {
+    std::fs::write(path, FLUSH_OBJ).map_err(CompileError::WriteHelper)
+}
+
+/// The linker given in the options, or the default one for the target.
+fn linker_path(options: &CompileOptions, default: &str) -> PathBuf {
+    options.linker.clone().unwrap_or_else(|| PathBuf::from(default))
+}
+
+/// Links a raw _start program, with the prebuilt flush helper unless the flushing is generated into the program.
+fn invoke_ld(obj_path: &Path, flush_path: Option<&Path>, out_path: &Path, options: &CompileOptions) -> Result<(), CompileError> {
+    let linker = linker_path(options, "ld");
+    let mut command = Command::new(&linker);
+    command.arg("-o").arg(out_path);
+    if options.static_link {
+        command.arg("-static");
+    }
+    else {
+        command.arg("-dynamic-linker").arg("/lib64/ld-linux-x86-64.so.2");
+    }
+    if options.codegen.pie() {
+        command.arg("-pie");
+    }
+    command.arg(obj_path);
+    if let Some(flush_path) = flush_path {
+        command.arg(flush_path);
+    }
+
+    match command
+        .arg("-lc")
+        .args(&options.linker_args)
+        .output() {
+        Ok(output) => {
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let mut msg = format!("Ld returned failure exit status:\n {}", stderr);
+                if options.static_link && is_static_glibc_failure(&stderr) {
+                    msg.push_str(
+                        "\nThe static glibc has to be started by the C runtime, so it needs --entry main. \
+                        Static C libraries that don't, like musl, can be linked with --linker-arg -L<dir>."
+                    );
+                }
+                return Err(CompileError::Ld(msg));
+            }
+        }
+        Err(err) => {
+            return Err(CompileError::Ld(format!("Failed to invoke {} command: {}", linker.display(), err)));
+        }
+    };
+
+    Ok(())
+}
+
+/// Whether linking a raw _start program failed because the static glibc needs what only the C runtime startup files
+/// and the compiler's support libraries provide.
+fn is_static_glibc_failure(stderr: &str) -> bool {
+    ["_Unwind_Resume", "__gcc_personality_v0", "undefined reference to `_init'", "__libc_start_main"]
+        .iter()
+        .any(|symptom| stderr.contains(symptom))
+}
+
+/// Links through the system C compiler, which adds the C runtime startup files that call main.
+fn invoke_cc(obj_path: &Path, out_path: &Path, options: &CompileOptions) -> Result<(), CompileError> {
+    let linker = linker_path(options, "cc");
+    let mut command = Command::new(&linker);
+    command.arg("-o").arg(out_path);
+    command.arg(if options.codegen.pie() { "-pie" } else { "-no-pie" });
+    if options.static_link {
+        command.arg("-static");
+    }
+
+    match command
+        .arg(obj_path)
+        .args(&options.linker_args)
+        .output() {
+        Ok(output) => {
+            if !output.status.success() {
+                let msg = format!("Cc returned failure exit status:\n {}", String::from_utf8_lossy(&output.stderr));
+                return Err(CompileError::Ld(msg));
+            }
+        }
+        Err(err) => {
+            return Err(CompileError::Ld(format!("Failed to invoke {} command: {}", linker.display(), err)));
+        }
+    };
+
+    Ok(())
+}
+
+/// Links a Windows executable against the static MSVC C runtime, which provides the entry point calling main.
+fn invoke_lld_link(obj_path: &Path, out_path: &Path, options: &CompileOptions) -> Result<(), CompileError> {
+    let mut out_arg = OsString::from("/out:");
+    out_arg.push(out_path);
+
+    let linker = linker_path(options, "lld-link");
+    match Command::new(&linker)
+        .arg(out_arg)
+        .arg("/subsystem:console")
+        .arg("/defaultlib:libcmt")
+        .arg("/defaultlib:oldnames")
+        .arg(obj_path)
+        .args(&options.linker_args)
+        .output() {
+        Ok(output) => {
+            if !output.status.success() {
+                let msg = format!("Lld-link returned failure exit status:\n {}", String::from_utf8_lossy(&output.stderr));
+                return Err(CompileError::Ld(msg));
+            }
+        }
+        Err(err) => {
+            return Err(CompileError::Ld(format!("Failed to invoke {} command: {}", linker.display(), err)));
+        }
+    };
+
+    Ok(())
+}
+/// Links a wasm module exporting run, turning the functions the host has to provide into imports.
+fn invoke_wasm_ld(obj_path: &Path, out_path: &Path, options: &CompileOptions) -> Result<(), CompileError> {
+    let linker = linker_path(options, "wasm-ld");
+    match Command::new(&linker)
+        .arg("-o").arg(out_path)
+        .arg("--no-entry")
+        .arg("--export=run")
+        .arg("--allow-undefined")
+        .arg(obj_path)
+        .args(&options.linker_args)
+        .output() {
+        Ok(output) => {
+            if !output.status.success() {
+                let msg = format!("Wasm-ld returned failure exit status:\n {}", String::from_utf8_lossy(&output.stderr));
+                return Err(CompileError::Ld(msg));
+            }
+        }
+        Err(err) => {
+            return Err(CompileError::Ld(format!("Failed to invoke {} command: {}", linker.display(), err)));
+        }
+    };
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(output_path: Option<&str>, is_existing_dir: bool) -> (PathBuf, OsString) {
+        resolve_executable_path(output_path.map(Path::new), OsStr::new("prog"), is_existing_dir)
+    }
+
+    #[test]
+    fn no_output_path_uses_current_directory() {
+        assert_eq!(resolve(None, false), (PathBuf::from("./prog"), OsString::from("prog")));
+    }
+
+    #[test]
+    fn output_path_ending_in_separator_is_directory() {
+        assert_eq!(resolve(Some("dir/"), false), (PathBuf::from("dir/prog"), OsString::from("prog")));
+    }
+
+    #[test]
+    fn existing_directory_gets_executable_inside() {
+        assert_eq!(resolve(Some("dir"), true), (PathBuf::from("dir/prog"), OsString::from("prog")));
+    }
+
+    #[test]
+    fn output_path_names_executable() {
+        assert_eq!(resolve(Some("dir/name"), false), (PathBuf::from("dir/name"), OsString::from("name")));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::interpreter::{EofPolicy, DEBUG_RADIUS};
 use inkwell::context::{Context, ContextRef};
 use inkwell::module::Module;
 use inkwell::builder::Builder;
@@ -8,23 +9,186 @@ use inkwell::targets::TargetData;
 use inkwell::AtomicRMWBinOp::Add;
 use inkwell::memory_buffer::MemoryBuffer;
 use inkwell::basic_block::BasicBlock;
+use inkwell::types::{IntType, PointerType};
+use inkwell::OptimizationLevel;
+use inkwell::targets::{Target, TargetMachine, TargetTriple, InitializationConfig, RelocMode, CodeModel, FileType};
+use std::str::FromStr;
+use std::cell::Cell;
+use std::fmt::Write;
 
+pub use error::CompileError;
+pub use executable::{compile_to_executable, compile_to_executable_timed, CompileOptions, LLVM_MAJOR_VERSION};
+pub use c_backend::compile_to_c;
+use debug_info::DebugInfo;
 
-pub fn compile_to_ir(node: &InstructionNode, module_name: &str) -> MemoryBuffer {
+mod c_backend;
+mod error;
+mod executable;
+mod debug_info;
+
+
+pub fn compile_to_ir(node: &InstructionNode, module_name: &str, options: &CodegenOptions) -> MemoryBuffer {
     let context = Context::create();
-    let ctx = CompilationContext::new(module_name, &context);
-    let symbols = Symbols::new(&ctx);
+    let ctx = CompilationContext::new(module_name, &context, options, options.entry_point());
+    ctx.module.set_triple(&options.target_os.triple());
+    build_module(&ctx, node);
+
+    ctx.module.write_bitcode_to_memory()
+}
 
-    let entry = build_entry_block(&ctx, &symbols);
-    let vars = build_variables(&ctx, &symbols);
-    init_variables(&ctx, &symbols, &vars, entry);
+/// Like compile_to_ir, but returns the module as human-readable LLVM IR.
+pub fn compile_to_ir_text(node: &InstructionNode, module_name: &str, options: &CodegenOptions) -> String {
+    let context = Context::create();
+    let ctx = CompilationContext::new(module_name, &context, options, options.entry_point());
+    ctx.module.set_triple(&options.target_os.triple());
+    build_module(&ctx, node);
 
-    build_node(&ctx, &symbols, &vars, node);
+    ctx.module.print_to_string().to_string()
+}
 
-    free_variables(&ctx, &symbols, &vars);
-    exit_program(&ctx, &symbols);
+/// Like compile_to_ir_text, but only lists the basic blocks of each function defined in the module,
+/// each with the blocks its terminator can branch to, for checking how loops and bounds checks are lowered.
+/// Conditional branches list the block taken when the condition holds first.
+pub fn compile_to_cfg_text(node: &InstructionNode, module_name: &str, options: &CodegenOptions) -> String {
+    let context = Context::create();
+    let ctx = CompilationContext::new(module_name, &context, options, options.entry_point());
+    ctx.module.set_triple(&options.target_os.triple());
+    build_module(&ctx, node);
+
+    let mut out = String::new();
+    let mut function = ctx.module.get_first_function();
+    while let Some(current) = function {
+        let blocks = current.get_basic_blocks();
+
+        // Declarations of outside functions like malloc have no blocks to show.
+        if !blocks.is_empty() {
+            writeln!(out, "{}:", current.get_name().to_string_lossy()).unwrap();
+            for block in blocks {
+                writeln!(out, "  {} -> {}", block.get_name().to_string_lossy(), block_successors(block)).unwrap();
+            }
+            out.push('\n');
+        }
 
-    ctx.module.write_bitcode_to_memory()
+        function = current.get_next_function();
+    }
+
+    out
+}
+
+/// The names of the blocks the terminator of the block can branch to, or how it leaves the function.
+fn block_successors(block: BasicBlock) -> String {
+    let terminator = match block.get_terminator() {
+        Some(terminator) => terminator,
+        None => return String::from("(no terminator)"),
+    };
+
+    let mut targets: Vec<String> = (0..terminator.get_num_operands())
+        .filter_map(|i| terminator.get_operand(i))
+        .filter_map(|operand| operand.right())
+        .map(|target| target.get_name().to_string_lossy().into_owned())
+        .collect();
+
+    match terminator.get_opcode() {
+        InstructionOpcode::Return => String::from("(return)"),
+        InstructionOpcode::Unreachable => String::from("(unreachable)"),
+        // LLVM keeps the false target of a conditional branch in front of the true one.
+        InstructionOpcode::Br if targets.len() == 2 => {
+            targets.reverse();
+            targets.join(", ")
+        }
+        _ => targets.join(", "),
+    }
+}
+
+/// Compiles the program straight to a native object file for the target OS,
+/// using LLVM in-process instead of writing bitcode and invoking llc.
+/// The optimization level can be any of 0, 1, 2, 3, like llc's -O flag.
+pub fn compile_to_object(node: &InstructionNode, module_name: &str, options: &CodegenOptions, opt_level: u32) -> Result<Vec<u8>, String> {
+    let context = Context::create();
+    let ctx = CompilationContext::new(module_name, &context, options, options.entry_point());
+    let triple = options.target_os.triple();
+    ctx.module.set_triple(&triple);
+    build_module(&ctx, node);
+
+    let opt_level = match opt_level {
+        0 => OptimizationLevel::None,
+        1 => OptimizationLevel::Less,
+        2 => OptimizationLevel::Default,
+        3 => OptimizationLevel::Aggressive,
+        _ => return Err(format!("Invalid optimization level: {}", opt_level)),
+    };
+
+    match options.target_os {
+        TargetOs::Linux => Target::initialize_native(&InitializationConfig::default())?,
+        TargetOs::Windows => Target::initialize_x86(&InitializationConfig::default()),
+        TargetOs::Wasm32 => Target::initialize_webassembly(&InitializationConfig::default()),
+    }
+    let target = Target::from_triple(&triple).map_err(|err| err.to_string())?;
+    let reloc_mode = if options.pie() { RelocMode::PIC } else { RelocMode::Default };
+    let machine = target.create_target_machine(&triple, "generic", "", opt_level, reloc_mode, CodeModel::Default)
+        .ok_or_else(|| format!("Failed to create target machine for {:?}", triple))?;
+
+    let object = machine.write_to_memory_buffer(&ctx.module, FileType::Object)
+        .map_err(|err| err.to_string())?;
+
+    Ok(object.as_slice().to_vec())
+}
+
+/// Compiles the program in memory and runs it right away using LLVM's JIT,
+/// returning its exit code: 0, or the current cell with exit_from_cell.
+/// No intermediate files are written and neither llc nor ld are needed.
+/// The program is built like a library function, so a failing program returns an error
+/// instead of exiting the whole process. That makes -1 unavailable as an exit code from the cell.
+pub fn jit_run(node: &InstructionNode, module_name: &str, options: &CodegenOptions) -> Result<i32, String> {
+    let context = Context::create();
+    let ctx = CompilationContext::new(module_name, &context, options, EntryPoint::Library);
+    build_module(&ctx, node);
+
+    let engine = ctx.module.create_jit_execution_engine(OptimizationLevel::None)
+        .map_err(|err| err.to_string())?;
+
+    let run = unsafe { engine.get_function::<unsafe extern "C" fn() -> i32>("bf_run") }
+        .map_err(|err| format!("Failed to look up bf_run function: {:?}", err))?;
+
+    match unsafe { run.call() } {
+        -1 => Err(String::from("The program failed with a runtime error")),
+        code => Ok(code),
+    }
+}
+
+
+
+fn build_module<'ctx>(ctx: &CompilationContext<'ctx>, node: &InstructionNode) -> Symbols<'ctx> {
+    let mut symbols = Symbols::new(ctx);
+    // Only declared when needed, so programs without debug commands don't depend on dprintf.
+    if uses_debug_commands(node) {
+        symbols.build_debug_dump(ctx);
+    }
+
+    let entry = build_entry_block(ctx, &symbols);
+    let vars = build_variables(ctx, &symbols);
+    init_variables(ctx, &symbols, &vars, entry);
+
+    build_node(ctx, &symbols, &vars, node);
+
+    let exit_code = build_exit_code(ctx, &symbols, &vars);
+    free_variables(ctx, &symbols, &vars);
+    return_from_entry(ctx, &symbols, exit_code);
+
+    if let Some(debug) = &ctx.debug {
+        debug.finalize();
+    }
+
+    symbols
+}
+
+fn uses_debug_commands(node: &InstructionNode) -> bool {
+    match &node.node_type {
+        NodeType::Program(children) => children.iter().any(uses_debug_commands),
+        NodeType::Loop(children) => children.iter().any(uses_debug_commands),
+        NodeType::Debug => true,
+        _ => false,
+    }
 }
 
 fn build_entry_block<'ctx>(ctx: &CompilationContext<'ctx>, symbols: &Symbols) -> BasicBlock<'ctx>{
@@ -38,12 +202,14 @@ fn build_variables<'ctx>(ctx: &CompilationContext<'ctx>, symbols: &Symbols) -> V
 fn init_variables(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, entry: BasicBlock) {
     ctx.builder.position_at_end(entry);
 
-    let val_30000 = ctx.context.i64_type().const_int(30000, false);
+    let tape_size = ctx.options.tape_size;
+    let val_tape_size = ctx.context.i64_type().const_int(tape_size, false);
+    let val_tape_bytes = ctx.context.i64_type().const_int(tape_size * ctx.options.cell_width.bytes(), false);
     let val_0 = ctx.context.i64_type().const_int(0, false);
-    let val_0_32 = ctx.context.i32_type().const_int(0, false);
-    let i8_ptr_type = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
+    let val_fill = ctx.context.i8_type().const_int(ctx.options.init_cell as u64, false);
+    let not_volatile = ctx.context.bool_type().const_int(0, false);
 
-    ctx.builder.build_store(vars.len, val_30000);
+    ctx.builder.build_store(vars.len, val_tape_size);
     ctx.builder.build_store(vars.index, val_0);
 
     let success = ctx.context.append_basic_block(symbols.start, "alloc_success");
@@ -51,24 +217,41 @@ fn init_variables(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables,
 
 
 
-    let alloc_result = ctx.builder.build_call(symbols.malloc, &[val_30000.into()], "alloc_result");
-    let alloc_ret_val = alloc_result.as_any_value_enum().into_pointer_value();
-
-    let is_nullptr = ctx.builder.build_is_null(alloc_ret_val, "is_nullptr");
-    ctx.builder.build_conditional_branch(is_nullptr, failed, success);
+    let (alloc_ret_val, has_succeeded) = match ctx.options.target_os {
+        TargetOs::Wasm32 => {
+            let heap_base = ctx.module.add_global(ctx.context.i8_type(), None, "__heap_base");
+            let tape_start = heap_base.as_pointer_value();
+            let has_grown = ctx.builder.build_call(symbols.grow_memory(), &[tape_start.into(), val_tape_bytes.into()], "has_grown");
+            (tape_start, has_grown.as_any_value_enum().into_int_value())
+        }
+        _ => {
+            let alloc_result = ctx.builder.build_call(symbols.malloc, &[val_tape_bytes.into()], "alloc_result");
+            let alloc_ret_val = alloc_result.as_any_value_enum().into_pointer_value();
+            (alloc_ret_val, ctx.builder.build_is_not_null(alloc_ret_val, "is_not_null"))
+        }
+    };
+    ctx.builder.build_conditional_branch(has_succeeded, success, failed);
 
     ctx.builder.position_at_end(failed);
-    let error_msg = ctx.builder.build_pointer_cast(symbols.alloc_failed.as_pointer_value(), i8_ptr_type, "err_msg");
-    ctx.builder.build_call(symbols.puts, &[error_msg.into()], "");
-    exit_program(ctx, symbols);
+    build_error_message(ctx, symbols, symbols.alloc_failed);
+    fail_program(ctx, symbols);
 
 
     ctx.builder.position_at_end(success);
-    ctx.builder.build_store(vars.array, alloc_ret_val);
-    ctx.builder.build_call(symbols.memset, &[alloc_ret_val.into(), val_0_32.into(), val_30000.into()], "");
+    let arr_ptr = ctx.builder.build_pointer_cast(alloc_ret_val, ctx.cell_ptr_type(), "arr_ptr");
+    ctx.builder.build_store(vars.array, arr_ptr);
+    // Fresh linear memory is already zeroed.
+    if ctx.options.target_os != TargetOs::Wasm32 {
+        ctx.builder.build_call(symbols.memset, &[alloc_ret_val.into(), val_fill.into(), val_tape_bytes.into(), not_volatile.into()], "");
+    }
+    ctx.slack.set(Some(tape_size as isize - 1));
 }
 
 fn build_node(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, node: &InstructionNode) {
+    if let Some(debug) = &ctx.debug {
+        debug.set_location(ctx.context, &ctx.builder, node);
+    }
+
     match &node.node_type {
         NodeType::Program(children) => {
             for child in children {
@@ -82,7 +265,14 @@ fn build_node(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, nod
         NodeType::Decrement(amount) => build_decrement(ctx, symbols, vars, *amount),
         NodeType::Output => build_output(ctx, symbols, vars),
         NodeType::Input => build_input(ctx, symbols, vars),
+        NodeType::Debug => build_debug(ctx, symbols, vars, node),
         NodeType::SetCell(value) => build_set(ctx, symbols, vars, *value),
+        NodeType::MultiplyAdd { targets } => build_multiply_add(ctx, symbols, vars, targets),
+        NodeType::MoveAdd { offset } => build_move_add(ctx, symbols, vars, *offset),
+        NodeType::ScanRight(stride) => build_scan(ctx, symbols, vars, *stride as isize),
+        NodeType::ScanLeft(stride) => build_scan(ctx, symbols, vars, -(*stride as isize)),
+        NodeType::AddAtOffset { offset, amount } => build_add_at_offset(ctx, symbols, vars, *offset, *amount),
+        NodeType::SetAtOffset { offset, value } => build_set_at_offset(ctx, symbols, vars, *offset, *value),
     }
 }
 fn build_loop(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, children: &Vec<InstructionNode>) {
@@ -91,14 +281,16 @@ fn build_loop(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, chi
     let loop_end = ctx.context.append_basic_block(symbols.start, "loop_end");
 
     ctx.builder.build_unconditional_branch(loop_header);
+    ctx.slack.set(None);
 
     ctx.builder.position_at_end(loop_header);
     build_resize(ctx, symbols, vars);
+    let header_slack = ctx.slack.get();
     let i_val = ctx.builder.build_load(vars.index, "index_val");
     let arr_ptr = ctx.builder.build_load(vars.array, "arr_ptr");
     let cell_ptr = unsafe { ctx.builder.build_gep(arr_ptr.into_pointer_value(), &[i_val.into_int_value()], "cell_ptr") };
     let cell_val = ctx.builder.build_load(cell_ptr, "cell_val");
-    let val_0 = ctx.context.i8_type().const_int(0, false);
+    let val_0 = ctx.cell_type().const_int(0, false);
     let is_zero = ctx.builder.build_int_compare(IntPredicate::EQ, cell_val.into_int_value(), val_0.into(), "is_zero");
     ctx.builder.build_conditional_branch(is_zero, loop_end, loop_body);
 
@@ -111,52 +303,640 @@ fn build_loop(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, chi
 
 
     ctx.builder.position_at_end(loop_end);
+    ctx.slack.set(header_slack);
 }
 fn build_next(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, amount: usize) {
     let amount_val = ctx.context.i64_type().const_int(amount as u64, false);
-    ctx.builder.build_call(symbols.next(), &[vars.index.into(), amount_val.into()], "");
+    if ctx.options.inline_ops {
+        build_index_add(ctx, vars.index, amount_val);
+        ctx.move_slack(amount as isize);
+    }
+    else {
+        ctx.builder.build_call(symbols.next(), &[vars.index.into(), amount_val.into()], "");
+    }
 }
 fn build_previous(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, amount: usize) {
     let amount_val = ctx.context.i64_type().const_int(amount as u64, false);
-    ctx.builder.build_call(symbols.previous(), &[vars.array.into(), vars.len.into(), vars.index.into(), amount_val.into()], "");
+    if ctx.options.inline_ops {
+        build_index_sub(ctx, symbols, vars, amount_val);
+        ctx.move_slack(-(amount as isize));
+    }
+    else {
+        ctx.builder.build_call(symbols.previous(), &[vars.array.into(), vars.len.into(), vars.index.into(), amount_val.into()], "");
+    }
 }
 fn build_increment(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, amount: usize) {
-    let amount_val = ctx.context.i8_type().const_int(amount as u64 % 256, false);
-    let args: [BasicValueEnum; 4] = [vars.array.into(), vars.len.into(), vars.index.into(), amount_val.into()];
-    ctx.builder.build_call(symbols.increment(), &args, "");
+    let amount_val = ctx.cell_const(amount);
+    build_add(ctx, symbols, vars, amount_val);
+}
+/// Adds a runtime value to the current cell.
+fn build_add(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, amount_val: IntValue) {
+    if ctx.options.inline_ops {
+        build_resize(ctx, symbols, vars);
+        build_cell_add(ctx, vars, amount_val);
+    }
+    else {
+        let args: [BasicValueEnum; 4] = [vars.array.into(), vars.len.into(), vars.index.into(), amount_val.into()];
+        ctx.builder.build_call(symbols.increment(), &args, "");
+    }
 }
 fn build_decrement(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, amount: usize) {
-    let amount_val = ctx.context.i8_type().const_int(amount as u64 % 256, false);
-    let args: [BasicValueEnum; 4] = [vars.array.into(), vars.len.into(), vars.index.into(), amount_val.into()];
-    ctx.builder.build_call(symbols.decrement(), &args, "");
+    let amount_val = ctx.cell_const(amount);
+    if ctx.options.inline_ops {
+        build_resize(ctx, symbols, vars);
+        build_cell_sub(ctx, vars, amount_val);
+    }
+    else {
+        let args: [BasicValueEnum; 4] = [vars.array.into(), vars.len.into(), vars.index.into(), amount_val.into()];
+        ctx.builder.build_call(symbols.decrement(), &args, "");
+    }
 }
 fn build_output(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables) {
-    let args: [BasicValueEnum; 3] = [vars.array.into(), vars.len.into(), vars.index.into()];
-    ctx.builder.build_call(symbols.output(), &args, "");
+    if ctx.options.inline_ops {
+        build_resize(ctx, symbols, vars);
+        build_cell_output(ctx, symbols, vars);
+    }
+    else {
+        let args: [BasicValueEnum; 3] = [vars.array.into(), vars.len.into(), vars.index.into()];
+        ctx.builder.build_call(symbols.output(), &args, "");
+    }
 }
 fn build_input(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables) {
-    let args: [BasicValueEnum; 3] = [vars.array.into(), vars.len.into(), vars.index.into()];
-    ctx.builder.build_call(symbols.input(), &args, "");
+    if ctx.options.inline_ops {
+        build_resize(ctx, symbols, vars);
+        build_cell_input(ctx, symbols, vars);
+    }
+    else {
+        let args: [BasicValueEnum; 3] = [vars.array.into(), vars.len.into(), vars.index.into()];
+        ctx.builder.build_call(symbols.input(), &args, "");
+    }
 }
 fn build_set(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, value: usize) {
-    let value_val = ctx.context.i8_type().const_int(value as u64 % 256, false);
-    let args: [BasicValueEnum; 4] = [vars.array.into(), vars.len.into(), vars.index.into(), value_val.into()];
-    ctx.builder.build_call(symbols.set(), &args, "");
+    let value_val = ctx.cell_const(value);
+    if ctx.options.inline_ops {
+        build_resize(ctx, symbols, vars);
+        build_cell_store(ctx, vars, value_val);
+    }
+    else {
+        let args: [BasicValueEnum; 4] = [vars.array.into(), vars.len.into(), vars.index.into(), value_val.into()];
+        ctx.builder.build_call(symbols.set(), &args, "");
+    }
+}
+fn build_debug(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, node: &InstructionNode) {
+    let line_val = ctx.context.i32_type().const_int(node.line as u64, false);
+    let char_val = ctx.context.i32_type().const_int(node.char as u64, false);
+
+    ctx.builder.build_call(
+        symbols.debug_dump(),
+        &[vars.array.into(), vars.len.into(), vars.index.into(), line_val.into(), char_val.into()],
+        "",
+    );
 }
+fn build_multiply_add(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, targets: &[(isize, i32)]) {
+    let mul_body = ctx.context.append_basic_block(symbols.start, "mul_body");
+    let mul_end = ctx.context.append_basic_block(symbols.start, "mul_end");
+
+    build_resize(ctx, symbols, vars);
+    let i_val = ctx.builder.build_load(vars.index, "index_val");
+    let arr_ptr = ctx.builder.build_load(vars.array, "arr_ptr");
+    let cell_ptr = unsafe { ctx.builder.build_gep(arr_ptr.into_pointer_value(), &[i_val.into_int_value()], "cell_ptr") };
+    let cell_val = ctx.builder.build_load(cell_ptr, "cell_val").into_int_value();
+    let val_0 = ctx.cell_type().const_int(0, false);
+    let is_zero = ctx.builder.build_int_compare(IntPredicate::EQ, cell_val, val_0, "is_zero");
+    ctx.builder.build_conditional_branch(is_zero, mul_end, mul_body);
+    let skipped_slack = ctx.slack.get();
+
+
+    ctx.builder.position_at_end(mul_body);
+    for &(offset, factor) in targets {
+        let factor_val = ctx.cell_type().const_int(factor as i64 as u64, true);
+        let amount_val = ctx.builder.build_int_mul(cell_val, factor_val, "mul_amount");
+
+        build_offset_move(ctx, symbols, vars, offset);
+        build_add(ctx, symbols, vars, amount_val);
+        build_offset_move(ctx, symbols, vars, -offset);
+    }
+    ctx.builder.build_unconditional_branch(mul_end);
+
+
+    ctx.builder.position_at_end(mul_end);
+    ctx.merge_slack(skipped_slack);
+}
+fn build_move_add(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, offset: isize) {
+    let move_body = ctx.context.append_basic_block(symbols.start, "move_body");
+    let move_end = ctx.context.append_basic_block(symbols.start, "move_end");
+
+    build_resize(ctx, symbols, vars);
+    let i_val = ctx.builder.build_load(vars.index, "index_val");
+    let arr_ptr = ctx.builder.build_load(vars.array, "arr_ptr");
+    let cell_ptr = unsafe { ctx.builder.build_gep(arr_ptr.into_pointer_value(), &[i_val.into_int_value()], "cell_ptr") };
+    let cell_val = ctx.builder.build_load(cell_ptr, "cell_val").into_int_value();
+    let val_0 = ctx.cell_type().const_int(0, false);
+    let is_zero = ctx.builder.build_int_compare(IntPredicate::EQ, cell_val, val_0, "is_zero");
+    ctx.builder.build_conditional_branch(is_zero, move_end, move_body);
+    let skipped_slack = ctx.slack.get();
+
+
+    ctx.builder.position_at_end(move_body);
+    build_offset_move(ctx, symbols, vars, offset);
+    build_add(ctx, symbols, vars, cell_val);
+    build_offset_move(ctx, symbols, vars, -offset);
+    ctx.builder.build_unconditional_branch(move_end);
+
+
+    ctx.builder.position_at_end(move_end);
+    ctx.merge_slack(skipped_slack);
+}
+/// The pointer is moved to the offset and back around the operation, so bounds are checked as usual.
+/// With inline_ops, LLVM folds the moves into a single access at index + offset.
+fn build_add_at_offset(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, offset: isize, amount: i32) {
+    let amount_val = ctx.cell_type().const_int(amount as i64 as u64, true);
+
+    build_offset_move(ctx, symbols, vars, offset);
+    build_add(ctx, symbols, vars, amount_val);
+    build_offset_move(ctx, symbols, vars, -offset);
+}
+fn build_set_at_offset(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, offset: isize, value: usize) {
+    build_offset_move(ctx, symbols, vars, offset);
+    build_set(ctx, symbols, vars, value);
+    build_offset_move(ctx, symbols, vars, -offset);
+}
+fn build_scan(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, stride: isize) {
+    if stride == 1 && ctx.options.cell_width == CellWidth::Bits8 && ctx.options.target_os != TargetOs::Wasm32 && ctx.options.init_cell == 0 {
+        build_memchr_scan(ctx, symbols, vars);
+        return;
+    }
+
+    let scan_header = ctx.context.append_basic_block(symbols.start, "scan_header");
+    let scan_body = ctx.context.append_basic_block(symbols.start, "scan_body");
+    let scan_end = ctx.context.append_basic_block(symbols.start, "scan_end");
 
+    ctx.builder.build_unconditional_branch(scan_header);
+    ctx.slack.set(None);
+
+    ctx.builder.position_at_end(scan_header);
+    build_resize(ctx, symbols, vars);
+    let header_slack = ctx.slack.get();
+    let i_val = ctx.builder.build_load(vars.index, "index_val");
+    let arr_ptr = ctx.builder.build_load(vars.array, "arr_ptr");
+    let cell_ptr = unsafe { ctx.builder.build_gep(arr_ptr.into_pointer_value(), &[i_val.into_int_value()], "cell_ptr") };
+    let cell_val = ctx.builder.build_load(cell_ptr, "cell_val");
+    let val_0 = ctx.cell_type().const_int(0, false);
+    let is_zero = ctx.builder.build_int_compare(IntPredicate::EQ, cell_val.into_int_value(), val_0, "is_zero");
+    ctx.builder.build_conditional_branch(is_zero, scan_end, scan_body);
+
+
+    ctx.builder.position_at_end(scan_body);
+    build_offset_move(ctx, symbols, vars, stride);
+    ctx.builder.build_unconditional_branch(scan_header);
+
+
+    ctx.builder.position_at_end(scan_end);
+    ctx.slack.set(header_slack);
+}
+/// Scans right for a zero cell using memchr.
+/// If no zero is found within the allocated tape, the pointer is moved to the end of it,
+/// since cells beyond that are zero as well. Only used when the tape is filled with zero.
+fn build_memchr_scan(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables) {
+    let found = ctx.context.append_basic_block(symbols.start, "scan_found");
+    let not_found = ctx.context.append_basic_block(symbols.start, "scan_not_found");
+    let scan_end = ctx.context.append_basic_block(symbols.start, "scan_end");
+
+    build_resize(ctx, symbols, vars);
+    let i64_type = ctx.context.i64_type();
+    let i8_ptr_type = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
+    let i_val = ctx.builder.build_load(vars.index, "index_val").into_int_value();
+    let len_val = ctx.builder.build_load(vars.len, "len_val").into_int_value();
+    let arr_ptr = ctx.builder.build_load(vars.array, "arr_ptr").into_pointer_value();
+    let cell_ptr = unsafe { ctx.builder.build_gep(arr_ptr, &[i_val], "cell_ptr") };
+    let search_ptr = ctx.builder.build_pointer_cast(cell_ptr, i8_ptr_type, "search_ptr");
+    let remaining = ctx.builder.build_int_sub(len_val, i_val, "remaining");
+    let val_0_32 = ctx.context.i32_type().const_int(0, false);
+
+    let zero_ptr = ctx.builder.build_call(symbols.memchr, &[search_ptr.into(), val_0_32.into(), remaining.into()], "zero_ptr");
+    let zero_ptr = zero_ptr.as_any_value_enum().into_pointer_value();
+    let is_null = ctx.builder.build_is_null(zero_ptr, "is_null");
+    ctx.builder.build_conditional_branch(is_null, not_found, found);
+
+
+    ctx.builder.position_at_end(found);
+    let zero_addr = ctx.builder.build_ptr_to_int(zero_ptr, i64_type, "zero_addr");
+    let arr_addr = ctx.builder.build_ptr_to_int(arr_ptr, i64_type, "arr_addr");
+    let new_i = ctx.builder.build_int_sub(zero_addr, arr_addr, "new_i");
+    ctx.builder.build_store(vars.index, new_i);
+    ctx.builder.build_unconditional_branch(scan_end);
+
+
+    ctx.builder.position_at_end(not_found);
+    ctx.builder.build_store(vars.index, len_val);
+    ctx.builder.build_unconditional_branch(scan_end);
+
+
+    ctx.builder.position_at_end(scan_end);
+    // The index is either anywhere up to the end of the tape or just past it.
+    ctx.slack.set(None);
+}
+fn build_offset_move(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, offset: isize) {
+    if offset > 0 {
+        build_next(ctx, symbols, vars, offset as usize);
+    }
+    else if offset < 0 {
+        build_previous(ctx, symbols, vars, -offset as usize);
+    }
+}
+
+/// Makes sure the current cell is within the tape.
+/// With inline_ops, the bounds check is emitted inline and the resize function is only called when it fails.
+/// The check is left out entirely if the current cell is already known to be within the tape.
 fn build_resize(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables) {
-    ctx.builder.build_call(symbols.resize(), &[vars.array.into(), vars.len.into(), vars.index.into()], "");
+    if !ctx.options.inline_ops {
+        ctx.builder.build_call(symbols.resize(), &[vars.array.into(), vars.len.into(), vars.index.into()], "");
+        return;
+    }
+    if let Some(slack) = ctx.slack.get() {
+        if slack >= 0 {
+            return;
+        }
+    }
+
+    let function = current_function(ctx);
+    let do_resize = ctx.context.append_basic_block(function, "do_resize");
+    let resized = ctx.context.append_basic_block(function, "resized");
+
+    let len_val = ctx.builder.build_load(vars.len, "len_val").into_int_value();
+    let i_val = ctx.builder.build_load(vars.index, "index_val").into_int_value();
+    let needs = ctx.builder.build_int_compare(IntPredicate::UGE, i_val, len_val, "needs_resize");
+    ctx.builder.build_conditional_branch(needs, do_resize, resized);
+
+    ctx.builder.position_at_end(do_resize);
+    let has_resized = ctx.builder.build_call(symbols.resize(), &[vars.array.into(), vars.len.into(), vars.index.into()], "has_resized");
+    if ctx.entry == EntryPoint::Library {
+        let resize_failed = ctx.context.append_basic_block(function, "resize_failed");
+        let has_resized = has_resized.as_any_value_enum().into_int_value();
+        ctx.builder.build_conditional_branch(has_resized, resized, resize_failed);
+
+        ctx.builder.position_at_end(resize_failed);
+        free_variables(ctx, symbols, vars);
+        fail_program(ctx, symbols);
+    }
+    else {
+        ctx.builder.build_unconditional_branch(resized);
+    }
+
+    ctx.builder.position_at_end(resized);
+    ctx.slack.set(Some(0));
+}
+
+
+// Cell operations emitted at the current position, shared by the helper functions and inline_ops.
+// None of them check that the index is within the tape.
+
+fn current_function<'ctx>(ctx: &CompilationContext<'ctx>) -> FunctionValue<'ctx> {
+    ctx.builder.get_insert_block().unwrap().get_parent().unwrap()
+}
+fn build_cell_ptr<'ctx>(builder: &Builder<'ctx>, vars: &Variables<'ctx>) -> PointerValue<'ctx> {
+    let i_val = builder.build_load(vars.index, "index_val").into_int_value();
+    let arr_ptr = builder.build_load(vars.array, "arr_ptr").into_pointer_value();
+    unsafe { builder.build_gep(arr_ptr, &[i_val], "cell_ptr") }
+}
+fn build_index_add(ctx: &CompilationContext, index: PointerValue, amount_val: IntValue) {
+    let old_i = ctx.builder.build_load(index, "old_i").into_int_value();
+    let new_i = ctx.builder.build_int_add(old_i, amount_val, "new_i");
+    ctx.builder.build_store(index, new_i);
+}
+/// Moves the index left, exiting the program with an error message if it would underflow.
+fn build_index_sub(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables, amount_val: IntValue) {
+    let function = current_function(ctx);
+    let underflowed = ctx.context.append_basic_block(function, "underflowed");
+    let not_underflowed = ctx.context.append_basic_block(function, "not_underflowed");
+
+    let old_i = ctx.builder.build_load(vars.index, "old_i").into_int_value();
+    let is_underflow = ctx.builder.build_int_compare(IntPredicate::UGT, amount_val, old_i, "is_underflow");
+    ctx.builder.build_conditional_branch(is_underflow, underflowed, not_underflowed);
+
+    ctx.builder.position_at_end(underflowed);
+    build_error_message(ctx, symbols, symbols.index_underflow);
+    free_variables(ctx, symbols, vars);
+    fail_program(ctx, symbols);
+
+    ctx.builder.position_at_end(not_underflowed);
+    let new_i = ctx.builder.build_int_sub(old_i, amount_val, "new_i");
+    ctx.builder.build_store(vars.index, new_i);
+}
+fn build_cell_add(ctx: &CompilationContext, vars: &Variables, amount_val: IntValue) {
+    let cell_ptr = build_cell_ptr(&ctx.builder, vars);
+    let cell_val = ctx.builder.build_load(cell_ptr, "cell_val").into_int_value();
+    let new_cell_val = ctx.builder.build_int_add(cell_val, amount_val, "new_cell_val");
+    ctx.builder.build_store(cell_ptr, new_cell_val);
+}
+fn build_cell_sub(ctx: &CompilationContext, vars: &Variables, amount_val: IntValue) {
+    let cell_ptr = build_cell_ptr(&ctx.builder, vars);
+    let cell_val = ctx.builder.build_load(cell_ptr, "cell_val").into_int_value();
+    let new_cell_val = ctx.builder.build_int_sub(cell_val, amount_val, "new_cell_val");
+    ctx.builder.build_store(cell_ptr, new_cell_val);
+}
+fn build_cell_store(ctx: &CompilationContext, vars: &Variables, value_val: IntValue) {
+    let cell_ptr = build_cell_ptr(&ctx.builder, vars);
+    ctx.builder.build_store(cell_ptr, value_val);
+}
+fn build_cell_output(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables) {
+    let cell_ptr = build_cell_ptr(&ctx.builder, vars);
+    let cell_val = ctx.builder.build_load(cell_ptr, "cell_val");
+    let i32_type = ctx.context.i32_type();
+    let out_byte = ctx.builder.build_int_truncate_or_bit_cast(cell_val.into_int_value(), ctx.context.i8_type(), "out_byte");
+    let out_c = ctx.builder.build_int_z_extend(out_byte, i32_type, "out_c");
+    ctx.builder.build_call(symbols.putchar, &[out_c.into()], "");
+    if !ctx.options.buffered_output {
+        ctx.builder.build_call(symbols.flush_stdout, &[], "");
+    }
+}
+fn build_cell_input(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables) {
+    let function = current_function(ctx);
+    let not_eof = ctx.context.append_basic_block(function, "not_eof");
+    let input_complete = ctx.context.append_basic_block(function, "input_complete");
+
+    let is_eof_block = ctx.context.append_basic_block(function, "is_eof");
+
+    let in_c = ctx.builder.build_call(symbols.getchar, &[], "in_c");
+    let in_c = in_c.as_any_value_enum().into_int_value();
+    let val_0 = ctx.context.i32_type().const_int(0, false);
+    let is_eof = ctx.builder.build_int_compare(IntPredicate::SLT, in_c, val_0, "is_eof");
+    ctx.builder.build_conditional_branch(is_eof, is_eof_block, not_eof);
+
+    ctx.builder.position_at_end(is_eof_block);
+    let eof_val = match ctx.options.eof_policy {
+        EofPolicy::Unchanged => None,
+        EofPolicy::Zero => Some(ctx.cell_type().const_int(0, false)),
+        EofPolicy::NegativeOne => Some(ctx.cell_type().const_all_ones()),
+    };
+    if let Some(eof_val) = eof_val {
+        build_cell_store(ctx, vars, eof_val);
+    }
+    ctx.builder.build_unconditional_branch(input_complete);
+
+    ctx.builder.position_at_end(not_eof);
+    let new_cell_val = ctx.builder.build_int_truncate_or_bit_cast(in_c, ctx.cell_type(), "new_cell_value");
+    build_cell_store(ctx, vars, new_cell_val);
+    ctx.builder.build_unconditional_branch(input_complete);
+
+    ctx.builder.position_at_end(input_complete);
 }
 
 
 fn free_variables(ctx: &CompilationContext, symbols: &Symbols, vars: &Variables) {
-    let arr_ptr = ctx.builder.build_load(vars.array, "arr_ptr");
-    ctx.builder.build_call(symbols.free, &[arr_ptr.into()], "");
+    // The tape of a wasm module is part of its linear memory, which can't be given back.
+    if ctx.options.target_os == TargetOs::Wasm32 {
+        return;
+    }
+
+    let arr_ptr = ctx.builder.build_load(vars.array, "arr_ptr").into_pointer_value();
+    let i8_ptr_type = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
+    let raw_ptr = ctx.builder.build_pointer_cast(arr_ptr, i8_ptr_type, "raw_ptr");
+    ctx.builder.build_call(symbols.free, &[raw_ptr.into()], "");
+}
+/// Writes one of the constant error messages to stderr, keeping stdout clean for the program's own output.
+fn build_error_message(ctx: &CompilationContext, symbols: &Symbols, message: GlobalValue) {
+    let i8_ptr_type = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
+    let msg_ptr = ctx.builder.build_pointer_cast(message.as_pointer_value(), i8_ptr_type, "err_msg");
+
+    // The constant is an array including the nul terminator, which isn't written.
+    let msg_len = message.as_pointer_value().get_type().get_element_type().into_array_type().len() - 1;
+    let len_val = ctx.context.i64_type().const_int(msg_len as u64, false);
+    let stderr_fd = ctx.context.i32_type().const_int(2, false);
+    ctx.builder.build_call(symbols.write, &[stderr_fd.into(), msg_ptr.into(), len_val.into()], "");
 }
+/// Exits with code 0, which is also what failing programs exit with.
 fn exit_program(ctx: &CompilationContext, symbols: &Symbols) {
     let val_0_32 = ctx.context.i32_type().const_int(0, false);
-    ctx.builder.build_call(symbols.exit, &[val_0_32.into()], "");
-    ctx.builder.build_return(None);
+    exit_program_with_code(ctx, symbols, val_0_32);
+}
+/// Wasm modules can't exit the host, so they trap instead, ending the call to run with an error.
+fn exit_program_with_code(ctx: &CompilationContext, symbols: &Symbols, exit_code: IntValue) {
+    flush_buffered_output(ctx, symbols);
+    if ctx.options.target_os != TargetOs::Wasm32 {
+        ctx.builder.build_call(symbols.exit, &[exit_code.into()], "");
+    }
+    ctx.builder.build_unreachable();
+}
+/// Ends the program after an error: library functions return -1 to their caller, everything else exits.
+fn fail_program(ctx: &CompilationContext, symbols: &Symbols) {
+    if ctx.entry == EntryPoint::Library {
+        flush_buffered_output(ctx, symbols);
+        let val_minus_1 = ctx.context.i32_type().const_int(-1i64 as u64, true);
+        ctx.builder.build_return(Some(&val_minus_1));
+    }
+    else {
+        exit_program(ctx, symbols);
+    }
+}
+fn return_from_entry(ctx: &CompilationContext, symbols: &Symbols, exit_code: IntValue) {
+    match ctx.entry {
+        EntryPoint::Start => exit_program_with_code(ctx, symbols, exit_code),
+        EntryPoint::Main | EntryPoint::Library => {
+            flush_buffered_output(ctx, symbols);
+            ctx.builder.build_return(Some(&exit_code));
+        }
+    }
+}
+/// The i32 the program exits with once it finishes: 0, or with exit_from_cell the current cell.
+/// Has to be built before the tape is freed.
+fn build_exit_code<'ctx>(ctx: &CompilationContext<'ctx>, symbols: &Symbols, vars: &Variables<'ctx>) -> IntValue<'ctx> {
+    let i32_type = ctx.context.i32_type();
+    if !ctx.options.exit_from_cell {
+        return i32_type.const_int(0, false);
+    }
+
+    // The pointer may have moved past the end of the tape without touching the cell.
+    build_resize(ctx, symbols, vars);
+    let cell_ptr = build_cell_ptr(&ctx.builder, vars);
+    let cell_val = ctx.builder.build_load(cell_ptr, "exit_cell").into_int_value();
+    ctx.builder.build_int_z_extend_or_bit_cast(cell_val, i32_type, "exit_code")
+}
+/// With buffered output, the output is only flushed once the program ends, including when it ends with an error.
+fn flush_buffered_output(ctx: &CompilationContext, symbols: &Symbols) {
+    if ctx.options.buffered_output {
+        ctx.builder.build_call(symbols.flush_stdout, &[], "");
+    }
+}
+
+
+/// The width of a single cell on the tape of the compiled program.
+/// Arithmetic on cells wraps around at this width.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CellWidth {
+    Bits8,
+    Bits16,
+    Bits32,
+}
+impl CellWidth {
+    pub fn bits(self) -> u32 {
+        match self {
+            Self::Bits8 => 8,
+            Self::Bits16 => 16,
+            Self::Bits32 => 32,
+        }
+    }
+    pub fn bytes(self) -> u64 {
+        self.bits() as u64 / 8
+    }
+    pub fn modulus(self) -> u64 {
+        1 << self.bits()
+    }
+    /// Reduces a collapsed amount to the value it has on a cell of this width,
+    /// so a run of any length has the same net effect as in the interpreter.
+    pub fn wrap(self, amount: usize) -> u64 {
+        (amount as u64) % self.modulus()
+    }
+    /// The value of a cell with every one of its bytes set to the byte, like memset leaves it.
+    pub fn fill(self, byte: u8) -> u64 {
+        (byte as u64 * 0x0101_0101_0101_0101) % self.modulus()
+    }
+
+    fn int_type(self, context: &Context) -> IntType {
+        match self {
+            Self::Bits8 => context.i8_type(),
+            Self::Bits16 => context.i16_type(),
+            Self::Bits32 => context.i32_type(),
+        }
+    }
+}
+impl FromStr for CellWidth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "8" => Ok(Self::Bits8),
+            "16" => Ok(Self::Bits16),
+            "32" => Ok(Self::Bits32),
+            _ => Err(format!("Invalid cell width: {} (expected 8, 16 or 32)", s)),
+        }
+    }
+}
+
+
+/// The operating system compiled programs are meant to run on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TargetOs {
+    /// Linux with glibc, entered through a raw `_start`.
+    Linux,
+    /// 64 bit Windows with the MSVC C runtime, entered through `main`.
+    Windows,
+    /// A WebAssembly module for a host like a browser, entered through an exported `run` function.
+    /// The tape lives in the module's linear memory, starting at `__heap_base`,
+    /// and the host provides putchar, getchar and write as imports.
+    /// Since the tape isn't zeroed again, `run` should only be called once per instance.
+    Wasm32,
+}
+impl TargetOs {
+    fn triple(self) -> TargetTriple {
+        match self {
+            Self::Linux => TargetMachine::get_default_triple(),
+            Self::Windows => TargetTriple::create("x86_64-pc-windows-msvc"),
+            Self::Wasm32 => TargetTriple::create("wasm32-unknown-unknown"),
+        }
+    }
+}
+impl FromStr for TargetOs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linux" => Ok(Self::Linux),
+            "windows" => Ok(Self::Windows),
+            "wasm32" => Ok(Self::Wasm32),
+            _ => Err(format!("Invalid target: {} (expected linux, windows or wasm32)", s)),
+        }
+    }
+}
+
+
+/// The kind of function the program body is generated into.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EntryPoint {
+    /// A raw `_start` symbol that terminates the process by calling `exit`.
+    /// Needs the flush_stdout helper object to be linked in.
+    Start,
+    /// A C-style `main` that returns 0 once the program has finished,
+    /// meant to be linked with the C runtime startup files.
+    Main,
+    /// A function `int bf_run(void)` for calling the program from C or Rust, left in an object file to link into them.
+    /// Every call runs the whole program on a fresh tape, reading stdin and writing stdout through the C library.
+    /// It returns 0 once the program has finished, or the current cell with exit_from_cell,
+    /// and -1 if the program fails with a pointer underflow or the tape can't be allocated or grown, after freeing the tape.
+    /// Cell operations are always inlined, as if inline_ops was set.
+    Library,
+}
+impl FromStr for EntryPoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "start" | "_start" => Ok(Self::Start),
+            "main" => Ok(Self::Main),
+            "library" | "bf_run" => Ok(Self::Library),
+            _ => Err(format!("Invalid entry point: {} (expected start, main or library)", s)),
+        }
+    }
+}
+
+
+/// Settings that change the code generated for the program itself.
+#[derive(Copy, Clone, Debug)]
+pub struct CodegenOptions {
+    pub cell_width: CellWidth,
+    pub eof_policy: EofPolicy,
+    /// The OS to generate code for. Ignored by jit_run, which always runs on the host.
+    pub target_os: TargetOs,
+    /// The entry point to generate. Windows programs always use main, wasm modules a main-like run function.
+    pub entry_point: EntryPoint,
+    /// Emit cell operations directly into the program instead of calling the generated helper functions.
+    pub inline_ops: bool,
+    /// The number of cells allocated when the program starts.
+    pub tape_size: u64,
+    /// Emit DWARF line information pointing back to the brainfuck source.
+    /// The module name is used as the name of the source file.
+    pub debug_info: bool,
+    /// Only flush stdout when the program exits, instead of after every output instruction.
+    /// Much faster for programs writing a lot of output,
+    /// but output may not show up before the program waits for input.
+    pub buffered_output: bool,
+    /// The byte every byte of the tape is set to when it is allocated or grows.
+    /// Not supported for wasm32, whose fresh linear memory is always zero.
+    pub init_cell: u8,
+    /// Exit with the value of the current cell once the program finishes, instead of 0.
+    /// The OS usually only keeps the lowest 8 bits of it. Programs failing with an error still exit with 0.
+    pub exit_from_cell: bool,
+    /// Generate position independent code and link it into a position independent executable,
+    /// which the loader can put at a random address. Only used for linux.
+    pub pie: bool,
+}
+impl CodegenOptions {
+    pub fn entry_point(&self) -> EntryPoint {
+        match self.target_os {
+            TargetOs::Windows | TargetOs::Wasm32 => EntryPoint::Main,
+            TargetOs::Linux => self.entry_point,
+        }
+    }
+    pub fn pie(&self) -> bool {
+        self.pie && self.target_os == TargetOs::Linux
+    }
+}
+impl Default for CodegenOptions {
+    fn default() -> Self {
+        CodegenOptions {
+            cell_width: CellWidth::Bits8,
+            eof_policy: EofPolicy::Unchanged,
+            target_os: TargetOs::Linux,
+            entry_point: EntryPoint::Start,
+            inline_ops: false,
+            tape_size: 30000,
+            debug_info: false,
+            buffered_output: false,
+            init_cell: 0,
+            exit_from_cell: false,
+            pie: false,
+        }
+    }
 }
 
 
@@ -164,18 +944,60 @@ struct CompilationContext<'ctx> {
     context: &'ctx Context,
     module: Module<'ctx>,
     builder: Builder<'ctx>,
+    options: CodegenOptions,
+    entry: EntryPoint,
+    /// How many cells the index can move right at this point of the program and still be within the tape,
+    /// or None if that isn't known. Only tracked with inline_ops, where it allows leaving out bounds checks.
+    slack: Cell<Option<isize>>,
+    debug: Option<DebugInfo<'ctx>>,
 }
 impl<'ctx> CompilationContext<'ctx> {
-    pub fn new(module_name: &str, context: &'ctx Context) -> CompilationContext<'ctx> {
+    pub fn new(module_name: &str, context: &'ctx Context, options: &CodegenOptions, entry: EntryPoint) -> CompilationContext<'ctx> {
         let module = context.create_module(module_name);
         let builder = context.create_builder();
+        let debug = if options.debug_info {
+            Some(DebugInfo::new(context, &module, module_name))
+        }
+        else {
+            None
+        };
 
         CompilationContext {
             context,
             module,
             builder,
+            // Errors can only return from the library function when they happen in it, not in a helper it calls.
+            options: CodegenOptions {
+                inline_ops: options.inline_ops || entry == EntryPoint::Library,
+                ..*options
+            },
+            entry,
+            slack: Cell::new(None),
+            debug,
         }
     }
+
+    fn cell_type(&self) -> IntType<'ctx> {
+        self.options.cell_width.int_type(self.context)
+    }
+    fn cell_ptr_type(&self) -> PointerType<'ctx> {
+        self.cell_type().ptr_type(AddressSpace::Generic)
+    }
+    fn cell_const(&self, amount: usize) -> IntValue<'ctx> {
+        self.cell_type().const_int(self.options.cell_width.wrap(amount), false)
+    }
+
+    fn move_slack(&self, offset: isize) {
+        self.slack.set(self.slack.get().map(|slack| slack - offset));
+    }
+    /// Joins the slack of two paths of control flow meeting at the current position.
+    fn merge_slack(&self, other: Option<isize>) {
+        let merged = match (self.slack.get(), other) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            _ => None,
+        };
+        self.slack.set(merged);
+    }
 }
 
 struct Symbols<'ctx> {
@@ -185,10 +1007,11 @@ struct Symbols<'ctx> {
     free: FunctionValue<'ctx>,
     putchar: FunctionValue<'ctx>,
     getchar: FunctionValue<'ctx>,
-    puts: FunctionValue<'ctx>,
+    write: FunctionValue<'ctx>,
     exit: FunctionValue<'ctx>,
     memset: FunctionValue<'ctx>,
     memcpy: FunctionValue<'ctx>,
+    memchr: FunctionValue<'ctx>,
     flush_stdout: FunctionValue<'ctx>,
 
     alloc_failed: GlobalValue<'ctx>,
@@ -204,6 +1027,8 @@ struct Symbols<'ctx> {
     output: Option<FunctionValue<'ctx>>,
     input: Option<FunctionValue<'ctx>>,
     set: Option<FunctionValue<'ctx>>,
+    debug_dump: Option<FunctionValue<'ctx>>,
+    grow_memory: Option<FunctionValue<'ctx>>,
 }
 impl<'ctx> Symbols<'ctx> {
     pub fn new(ctx: &CompilationContext<'ctx>) -> Symbols<'ctx> {
@@ -212,10 +1037,11 @@ impl<'ctx> Symbols<'ctx> {
         let free = Self::build_free_function(ctx);
         let putchar = Self::build_putchar_function(ctx);
         let getchar = Self::build_getchar_function(ctx);
-        let puts = Self::build_puts(ctx);
+        let write = Self::build_write(ctx);
         let exit = Self::build_exit(ctx);
         let memset = Self::build_memset(ctx);
         let memcpy = Self::build_memcpy(ctx);
+        let memchr = Self::build_memchr(ctx);
         let flush_stdout = Self::build_flush_stdout(ctx);
 
         let alloc_failed = Self::build_const_str(ctx, "\nError: Failed to allocate cell array\n", "alloc_failed");
@@ -229,10 +1055,11 @@ impl<'ctx> Symbols<'ctx> {
             free,
             putchar,
             getchar,
-            puts,
+            write,
             exit,
             memset,
             memcpy,
+            memchr,
             flush_stdout,
 
             alloc_failed,
@@ -247,7 +1074,12 @@ impl<'ctx> Symbols<'ctx> {
             output: None,
             input: None,
             set: None,
+            debug_dump: None,
+            grow_memory: None,
         };
+        if ctx.options.target_os == TargetOs::Wasm32 {
+            symbols.build_grow_memory(ctx);
+        }
         symbols.build_resize(ctx);
         symbols.build_next(ctx);
         symbols.build_previous(ctx);
@@ -284,12 +1116,39 @@ impl<'ctx> Symbols<'ctx> {
     pub fn set(&self) -> FunctionValue {
         self.set.unwrap()
     }
+    pub fn debug_dump(&self) -> FunctionValue {
+        self.debug_dump.unwrap()
+    }
+    pub fn grow_memory(&self) -> FunctionValue {
+        self.grow_memory.unwrap()
+    }
 
     fn build_start_function(ctx: &CompilationContext<'ctx>) -> FunctionValue<'ctx> {
-        let void_t = ctx.context.void_type();
-        let fn_type = void_t.fn_type(&[], false);
+        let start = match ctx.entry {
+            EntryPoint::Start => {
+                let void_t = ctx.context.void_type();
+                let fn_type = void_t.fn_type(&[], false);
+                ctx.module.add_function("_start", fn_type, None)
+            }
+            EntryPoint::Main => {
+                let i32_t = ctx.context.i32_type();
+                let fn_type = i32_t.fn_type(&[], false);
+                let name = match ctx.options.target_os {
+                    TargetOs::Wasm32 => "run",
+                    _ => "main",
+                };
+                ctx.module.add_function(name, fn_type, None)
+            }
+            EntryPoint::Library => {
+                let i32_t = ctx.context.i32_type();
+                let fn_type = i32_t.fn_type(&[], false);
+                ctx.module.add_function("bf_run", fn_type, None)
+            }
+        };
 
-        let start = ctx.module.add_function("_start", fn_type, None);
+        if let Some(debug) = &ctx.debug {
+            debug.attach_entry(start, &start.get_name().to_string_lossy());
+        }
 
         start
     }
@@ -326,12 +1185,14 @@ impl<'ctx> Symbols<'ctx> {
 
         function
     }
-    fn build_puts(context: &CompilationContext<'ctx>) -> FunctionValue<'ctx> {
-        let ret_t = context.context.i32_type();
+    fn build_write(context: &CompilationContext<'ctx>) -> FunctionValue<'ctx> {
+        let ret_t = context.context.i64_type();
+        let i32_t = context.context.i32_type();
         let i8_ptr_t = context.context.i8_type().ptr_type(AddressSpace::Generic);
+        let size_t = context.context.i64_type();
 
-        let fn_type = ret_t.fn_type(&[i8_ptr_t.into()], false);
-        let function = context.module.add_function("puts", fn_type, None);
+        let fn_type = ret_t.fn_type(&[i32_t.into(), i8_ptr_t.into(), size_t.into()], false);
+        let function = context.module.add_function("write", fn_type, None);
 
 
         function
@@ -345,22 +1206,38 @@ impl<'ctx> Symbols<'ctx> {
 
         function
     }
+    /// The llvm.memset intrinsic, which LLVM can lower to inline stores or a call to memset as it sees fit.
     fn build_memset(ctx: &CompilationContext<'ctx>) -> FunctionValue<'ctx> {
-        let i8_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
-        let i32_t = ctx.context.i32_type();
+        let void_t = ctx.context.void_type();
+        let i8_t = ctx.context.i8_type();
+        let i8_ptr_t = i8_t.ptr_type(AddressSpace::Generic);
         let i64_t = ctx.context.i64_type();
+        let bool_t = ctx.context.bool_type();
 
-        let fn_type = i8_ptr_t.fn_type(&[i8_ptr_t.into(), i32_t.into(), i64_t.into()], false);
-        let function = ctx.module.add_function("memset", fn_type, None);
+        let fn_type = void_t.fn_type(&[i8_ptr_t.into(), i8_t.into(), i64_t.into(), bool_t.into()], false);
+        let function = ctx.module.add_function("llvm.memset.p0i8.i64", fn_type, None);
 
         function
     }
+    /// The llvm.memcpy intrinsic, see build_memset.
     fn build_memcpy(ctx: &CompilationContext<'ctx>) -> FunctionValue<'ctx> {
+        let void_t = ctx.context.void_type();
         let i8_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
         let i64_t = ctx.context.i64_type();
+        let bool_t = ctx.context.bool_type();
 
-        let fn_type = i8_ptr_t.fn_type(&[i8_ptr_t.into(), i8_ptr_t.into(), i64_t.into()], false);
-        let function = ctx.module.add_function("memcpy", fn_type, None);
+        let fn_type = void_t.fn_type(&[i8_ptr_t.into(), i8_ptr_t.into(), i64_t.into(), bool_t.into()], false);
+        let function = ctx.module.add_function("llvm.memcpy.p0i8.p0i8.i64", fn_type, None);
+
+        function
+    }
+    fn build_memchr(ctx: &CompilationContext<'ctx>) -> FunctionValue<'ctx> {
+        let i8_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
+        let i32_t = ctx.context.i32_type();
+        let i64_t = ctx.context.i64_type();
+
+        let fn_type = i8_ptr_t.fn_type(&[i8_ptr_t.into(), i32_t.into(), i64_t.into()], false);
+        let function = ctx.module.add_function("memchr", fn_type, None);
 
         function
     }
@@ -369,15 +1246,37 @@ impl<'ctx> Symbols<'ctx> {
         let fn_type = void_t.fn_type(&[], false);
         let function = ctx.module.add_function("flush_stdout", fn_type, None);
 
+        // Only raw _start programs link the prebuilt helper object, unless they are position independent,
+        // which the helper isn't compiled as. Everywhere else the helper is generated and flushes through the C runtime.
+        // Wasm hosts get every byte as soon as putchar is called, so there is nothing to flush.
+        if ctx.options.target_os == TargetOs::Wasm32 {
+            let entry = ctx.context.append_basic_block(function, "entry");
+            ctx.builder.position_at_end(entry);
+            ctx.builder.build_return(None);
+        }
+        else if ctx.entry != EntryPoint::Start || ctx.options.pie() {
+            let i32_t = ctx.context.i32_type();
+            let i8_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
+            let fflush_type = i32_t.fn_type(&[i8_ptr_t.into()], false);
+            let fflush = ctx.module.add_function("fflush", fflush_type, None);
+
+            let entry = ctx.context.append_basic_block(function, "entry");
+            ctx.builder.position_at_end(entry);
+            ctx.builder.build_call(fflush, &[i8_ptr_t.const_null().into()], "");
+            ctx.builder.build_return(None);
+        }
+
         function
     }
 
     fn build_resize(&mut self, ctx: &CompilationContext<'ctx>) {
-        let i8_ptr_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic).ptr_type(AddressSpace::Generic);
+        let cell_ptr_ptr_t = ctx.cell_ptr_type().ptr_type(AddressSpace::Generic);
         let i64_ptr_t = ctx.context.i64_type().ptr_type(AddressSpace::Generic);
-        let void_t = ctx.context.void_type();
+        let bool_t = ctx.context.bool_type();
 
-        let fn_type = void_t.fn_type(&[i8_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into()], false);
+        // Returns whether the tape could be grown, which only matters to library functions:
+        // everything else exits the program right here when it can't.
+        let fn_type = bool_t.fn_type(&[cell_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into()], false);
         let resize = ctx.module.add_function("resize", fn_type, None);
 
         let entry = ctx.context.append_basic_block(resize, "entry");
@@ -402,41 +1301,69 @@ impl<'ctx> Symbols<'ctx> {
 
 
         ctx.builder.position_at_end(do_resize);
-        let val_100 = ctx.context.i64_type().const_int(100, false);
-        let new_len = ctx.builder.build_int_add(i_val.into_int_value(), val_100.into(), "new_len");
-        let alloc_result = ctx.builder.build_call(self.malloc, &[new_len.into()], "new_arr");
-        let new_arr = alloc_result.as_any_value_enum().into_pointer_value();
-        let has_succeeded = ctx.builder.build_is_not_null(new_arr, "has_succeeded");
+        // Grow geometrically, so walking right across a large tape only resizes a logarithmic number of times.
+        let val_1 = ctx.context.i64_type().const_int(1, false);
+        let val_2 = ctx.context.i64_type().const_int(2, false);
+        let min_len = ctx.builder.build_int_add(i_val.into_int_value(), val_1, "min_len");
+        let doubled_len = ctx.builder.build_int_mul(len_val.into_int_value(), val_2, "doubled_len");
+        let use_doubled = ctx.builder.build_int_compare(IntPredicate::UGT, doubled_len, min_len, "use_doubled");
+        let new_len = ctx.builder.build_select(use_doubled, doubled_len, min_len, "new_len").into_int_value();
+        let cell_bytes = ctx.context.i64_type().const_int(ctx.options.cell_width.bytes(), false);
+        let new_bytes = ctx.builder.build_int_mul(new_len, cell_bytes, "new_bytes");
+        let i8_ptr_type = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
+        // Wasm modules grow the tape in place, since nothing else lives in linear memory behind it.
+        let (new_arr, has_succeeded) = match ctx.options.target_os {
+            TargetOs::Wasm32 => {
+                let old_arr = ctx.builder.build_load(array, "old_arr").into_pointer_value();
+                let old_arr = ctx.builder.build_pointer_cast(old_arr, i8_ptr_type, "old_raw");
+                let has_grown = ctx.builder.build_call(self.grow_memory(), &[old_arr.into(), new_bytes.into()], "has_grown");
+                (None, has_grown.as_any_value_enum().into_int_value())
+            }
+            _ => {
+                let alloc_result = ctx.builder.build_call(self.malloc, &[new_bytes.into()], "new_arr");
+                let new_arr = alloc_result.as_any_value_enum().into_pointer_value();
+                (Some(new_arr), ctx.builder.build_is_not_null(new_arr, "has_succeeded"))
+            }
+        };
         ctx.builder.build_conditional_branch(has_succeeded, alloc_success, alloc_failed);
 
 
 
         ctx.builder.position_at_end(alloc_failed);
-        let i8_ptr_type = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
-        let error_msg = ctx.builder.build_pointer_cast(self.resize_failed.as_pointer_value(), i8_ptr_type, "err_msg");
-        ctx.builder.build_call(self.puts, &[error_msg.into()], "");
-        free_variables(ctx, self, &Variables {
-            array,
-            len,
-            index,
-        });
-        exit_program(ctx, self);
+        build_error_message(ctx, self, self.resize_failed);
+        if ctx.entry == EntryPoint::Library {
+            // The caller frees the tape and returns -1 itself.
+            ctx.builder.build_return(Some(&bool_t.const_int(0, false)));
+        }
+        else {
+            free_variables(ctx, self, &Variables {
+                array,
+                len,
+                index,
+            });
+            exit_program(ctx, self);
+        }
 
 
         ctx.builder.position_at_end(alloc_success);
-        let old_arr = ctx.builder.build_load(array, "old_arr");
-        let old_len = len_val;
-        let val_0 = ctx.context.i32_type().const_int(0, false);
-        ctx.builder.build_call(self.memset, &[new_arr.into(), val_0.into(), new_len.into()], "");
-        ctx.builder.build_call(self.memcpy, &[new_arr.into(), old_arr.into(), old_len.into()], "");
-        ctx.builder.build_call(self.free, &[old_arr.into()], "");
-        ctx.builder.build_store(array, new_arr);
+        if let Some(new_arr) = new_arr {
+            let old_arr = ctx.builder.build_load(array, "old_arr").into_pointer_value();
+            let old_arr = ctx.builder.build_pointer_cast(old_arr, i8_ptr_type, "old_raw");
+            let old_bytes = ctx.builder.build_int_mul(len_val.into_int_value(), cell_bytes, "old_bytes");
+            let val_fill = ctx.context.i8_type().const_int(ctx.options.init_cell as u64, false);
+            let not_volatile = ctx.context.bool_type().const_int(0, false);
+            ctx.builder.build_call(self.memset, &[new_arr.into(), val_fill.into(), new_bytes.into(), not_volatile.into()], "");
+            ctx.builder.build_call(self.memcpy, &[new_arr.into(), old_arr.into(), old_bytes.into(), not_volatile.into()], "");
+            ctx.builder.build_call(self.free, &[old_arr.into()], "");
+            let new_arr = ctx.builder.build_pointer_cast(new_arr, ctx.cell_ptr_type(), "new_cells");
+            ctx.builder.build_store(array, new_arr);
+        }
         ctx.builder.build_store(len, new_len);
         ctx.builder.build_unconditional_branch(resized);
 
 
         ctx.builder.position_at_end(resized);
-        ctx.builder.build_return(None);
+        ctx.builder.build_return(Some(&bool_t.const_int(1, false)));
 
 
         self.resize = Some(resize);
@@ -456,10 +1383,7 @@ impl<'ctx> Symbols<'ctx> {
         let amount_val = next.get_nth_param(1).unwrap().into_int_value();
 
 
-
-        let old_i = ctx.builder.build_load(index, "old_i");
-        let new_i = ctx.builder.build_int_add(old_i.into_int_value(), amount_val, "new_i");
-        ctx.builder.build_store(index, new_i);
+        build_index_add(ctx, index, amount_val);
         ctx.builder.build_return(None);
 
         self.next = Some(next);
@@ -467,10 +1391,10 @@ impl<'ctx> Symbols<'ctx> {
     fn build_previous(&mut self, ctx: &CompilationContext<'ctx>) {
         let i64_ptr_t = ctx.context.i64_type().ptr_type(AddressSpace::Generic);
         let i64_t = ctx.context.i64_type();
-        let i8_ptr_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic).ptr_type(AddressSpace::Generic);
+        let cell_ptr_ptr_t = ctx.cell_ptr_type().ptr_type(AddressSpace::Generic);
         let void_t = ctx.context.void_type();
 
-        let fn_type = void_t.fn_type(&[i8_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into(), i64_t.into()], false);
+        let fn_type = void_t.fn_type(&[cell_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into(), i64_t.into()], false);
         let previous = ctx.module.add_function("previous", fn_type, None);
 
         let entry = ctx.context.append_basic_block(previous, "entry");
@@ -483,41 +1407,22 @@ impl<'ctx> Symbols<'ctx> {
         let amount_val = previous.get_nth_param(3).unwrap().into_int_value();
 
 
-        let underflowed = ctx.context.append_basic_block(previous, "underflowed");
-        let not_underflowed = ctx.context.append_basic_block(previous, "not_underflowed");
-
-
-        let old_i = ctx.builder.build_load(index, "old_i");
-
-        let is_underflow = ctx.builder.build_int_compare(IntPredicate::UGT, amount_val, old_i.into_int_value(), "is_underflow");
-        ctx.builder.build_conditional_branch(is_underflow, underflowed, not_underflowed);
-
-        ctx.builder.position_at_end(underflowed);
-        let i8_ptr_type = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
-        let error_msg = ctx.builder.build_pointer_cast(self.index_underflow.as_pointer_value(), i8_ptr_type, "err_msg");
-        ctx.builder.build_call(self.puts, &[error_msg.into()], "");
-        free_variables(ctx, self, &Variables {
+        build_index_sub(ctx, self, &Variables {
             array,
             len,
             index,
-        });
-        exit_program(ctx, self);
-
-
-        ctx.builder.position_at_end(not_underflowed);
-        let new_i = ctx.builder.build_int_sub(old_i.into_int_value(), amount_val, "new_i");
-        ctx.builder.build_store(index, new_i);
+        }, amount_val);
         ctx.builder.build_return(None);
 
         self.previous = Some(previous);
     }
     fn build_increment(&mut self, ctx: &CompilationContext<'ctx>) {
         let i64_ptr_t = ctx.context.i64_type().ptr_type(AddressSpace::Generic);
-        let i8_ptr_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic).ptr_type(AddressSpace::Generic);
-        let i8_t = ctx.context.i8_type();
+        let cell_ptr_ptr_t = ctx.cell_ptr_type().ptr_type(AddressSpace::Generic);
+        let cell_t = ctx.cell_type();
         let void_t = ctx.context.void_type();
 
-        let fn_type = void_t.fn_type(&[i8_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into(), i8_t.into()], false);
+        let fn_type = void_t.fn_type(&[cell_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into(), cell_t.into()], false);
         let increment = ctx.module.add_function("increment", fn_type, None);
 
         let entry = ctx.context.append_basic_block(increment, "entry");
@@ -531,13 +1436,11 @@ impl<'ctx> Symbols<'ctx> {
 
         ctx.builder.build_call(self.resize(), &[array.into(), len.into(), index.into()], "");
 
-        let i_val = ctx.builder.build_load(index, "i_val").into_int_value();
-        let arr_ptr = ctx.builder.build_load(array, "arr_ptr").into_pointer_value();
-        let cell_ptr = unsafe { ctx.builder.build_gep(arr_ptr, &[i_val], "cell_ptr") };
-
-        let cell_val = ctx.builder.build_load(cell_ptr, "cell_val").into_int_value();
-        let new_cell_val = ctx.builder.build_int_add(cell_val, amount_val, "new_cell_val");
-        ctx.builder.build_store(cell_ptr, new_cell_val);
+        build_cell_add(ctx, &Variables {
+            array,
+            len,
+            index,
+        }, amount_val);
         ctx.builder.build_return(None);
 
 
@@ -545,11 +1448,11 @@ impl<'ctx> Symbols<'ctx> {
     }
     fn build_decrement(&mut self, ctx: &CompilationContext<'ctx>) {
         let i64_ptr_t = ctx.context.i64_type().ptr_type(AddressSpace::Generic);
-        let i8_ptr_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic).ptr_type(AddressSpace::Generic);
-        let i8_t = ctx.context.i8_type();
+        let cell_ptr_ptr_t = ctx.cell_ptr_type().ptr_type(AddressSpace::Generic);
+        let cell_t = ctx.cell_type();
         let void_t = ctx.context.void_type();
 
-        let fn_type = void_t.fn_type(&[i8_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into(), i8_t.into()], false);
+        let fn_type = void_t.fn_type(&[cell_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into(), cell_t.into()], false);
         let decrement = ctx.module.add_function("decrement", fn_type, None);
 
         let entry = ctx.context.append_basic_block(decrement, "entry");
@@ -563,23 +1466,21 @@ impl<'ctx> Symbols<'ctx> {
 
         ctx.builder.build_call(self.resize(), &[array.into(), len.into(), index.into()], "");
 
-        let i_val = ctx.builder.build_load(index, "i_val").into_int_value();
-        let arr_ptr = ctx.builder.build_load(array, "arr_ptr").into_pointer_value();
-        let cell_ptr = unsafe { ctx.builder.build_gep(arr_ptr, &[i_val], "cell_ptr") };
-
-        let cell_val = ctx.builder.build_load(cell_ptr, "cell_val").into_int_value();
-        let new_cell_val = ctx.builder.build_int_sub(cell_val, amount_val, "new_cell_val");
-        ctx.builder.build_store(cell_ptr, new_cell_val);
+        build_cell_sub(ctx, &Variables {
+            array,
+            len,
+            index,
+        }, amount_val);
         ctx.builder.build_return(None);
 
         self.decrement = Some(decrement);
     }
     fn build_output(&mut self, ctx: &CompilationContext<'ctx>) {
         let i64_ptr_t = ctx.context.i64_type().ptr_type(AddressSpace::Generic);
-        let i8_ptr_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic).ptr_type(AddressSpace::Generic);
+        let cell_ptr_ptr_t = ctx.cell_ptr_type().ptr_type(AddressSpace::Generic);
         let void_t = ctx.context.void_type();
 
-        let fn_type = void_t.fn_type(&[i8_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into()], false);
+        let fn_type = void_t.fn_type(&[cell_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into()], false);
         let output = ctx.module.add_function("output", fn_type, None);
 
         let entry = ctx.context.append_basic_block(output, "entry");
@@ -593,15 +1494,11 @@ impl<'ctx> Symbols<'ctx> {
         ctx.builder.build_call(self.resize(), &[array.into(), len.into(), index.into()], "");
 
 
-        let i_val = ctx.builder.build_load(index, "i_val").into_int_value();
-        let arr_ptr = ctx.builder.build_load(array, "arr_ptr").into_pointer_value();
-        let cell_ptr = unsafe { ctx.builder.build_gep(arr_ptr, &[i_val], "cell_ptr") };
-
-        let cell_val = ctx.builder.build_load(cell_ptr, "cell_val");
-        let i32_type = ctx.context.i32_type();
-        let out_c = ctx.builder.build_int_cast(cell_val.into_int_value(), i32_type, "out_c");
-        ctx.builder.build_call(self.putchar, &[out_c.into()], "");
-        ctx.builder.build_call(self.flush_stdout, &[], "");
+        build_cell_output(ctx, self, &Variables {
+            array,
+            len,
+            index,
+        });
         ctx.builder.build_return(None);
 
 
@@ -609,10 +1506,10 @@ impl<'ctx> Symbols<'ctx> {
     }
     fn build_input(&mut self, ctx: &CompilationContext<'ctx>) {
         let i64_ptr_t = ctx.context.i64_type().ptr_type(AddressSpace::Generic);
-        let i8_ptr_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic).ptr_type(AddressSpace::Generic);
+        let cell_ptr_ptr_t = ctx.cell_ptr_type().ptr_type(AddressSpace::Generic);
         let void_t = ctx.context.void_type();
 
-        let fn_type = void_t.fn_type(&[i8_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into()], false);
+        let fn_type = void_t.fn_type(&[cell_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into()], false);
         let input = ctx.module.add_function("input", fn_type, None);
 
         let entry = ctx.context.append_basic_block(input, "entry");
@@ -626,35 +1523,22 @@ impl<'ctx> Symbols<'ctx> {
         ctx.builder.build_call(self.resize(), &[array.into(), len.into(), index.into()], "");
 
 
-        let not_eof = ctx.context.append_basic_block(input, "not_eof");
-        let input_complete = ctx.context.append_basic_block(input, "input_complete");
-
-        let in_c = ctx.builder.build_call(self.getchar, &[], "in_c");
-        let in_c = in_c.as_any_value_enum().into_int_value();
-        let val_0 = ctx.context.i32_type().const_int(0, false);
-        let is_eof = ctx.builder.build_int_compare(IntPredicate::SLT, in_c, val_0, "is_eof");
-        ctx.builder.build_conditional_branch(is_eof, input_complete, not_eof);
-
-        ctx.builder.position_at_end(not_eof);
-        let new_cell_val = ctx.builder.build_int_cast(in_c, ctx.context.i8_type(), "new_cell_value");
-        let arr_ptr = ctx.builder.build_load(array, "arr_ptr").into_pointer_value();
-        let i_val = ctx.builder.build_load(index, "index_val").into_int_value();
-        let cell_ptr = unsafe { ctx.builder.build_gep(arr_ptr, &[i_val], "cell_ptr")};
-        ctx.builder.build_store(cell_ptr, new_cell_val);
-        ctx.builder.build_unconditional_branch(input_complete);
-
-        ctx.builder.position_at_end(input_complete);
+        build_cell_input(ctx, self, &Variables {
+            array,
+            len,
+            index,
+        });
         ctx.builder.build_return(None);
 
         self.input = Some(input);
     }
     fn build_set(&mut self, ctx: &CompilationContext<'ctx>) {
         let i64_ptr_t = ctx.context.i64_type().ptr_type(AddressSpace::Generic);
-        let i8_ptr_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic).ptr_type(AddressSpace::Generic);
-        let i8_t = ctx.context.i8_type();
+        let cell_ptr_ptr_t = ctx.cell_ptr_type().ptr_type(AddressSpace::Generic);
+        let cell_t = ctx.cell_type();
         let void_t = ctx.context.void_type();
 
-        let fn_type = void_t.fn_type(&[i8_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into(), i8_t.into()], false);
+        let fn_type = void_t.fn_type(&[cell_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into(), cell_t.into()], false);
         let set = ctx.module.add_function("set", fn_type, None);
 
         let entry = ctx.context.append_basic_block(set, "entry");
@@ -669,16 +1553,160 @@ impl<'ctx> Symbols<'ctx> {
         ctx.builder.build_call(self.resize(), &[array.into(), len.into(), index.into()], "");
 
 
-        let arr_ptr = ctx.builder.build_load(array, "arr_ptr").into_pointer_value();
-        let i_val = ctx.builder.build_load(index, "index_val").into_int_value();
-        let cell_ptr = unsafe { ctx.builder.build_gep(arr_ptr, &[i_val], "cell_ptr")};
-        ctx.builder.build_store(cell_ptr, value_val);
+        build_cell_store(ctx, &Variables {
+            array,
+            len,
+            index,
+        }, value_val);
         ctx.builder.build_return(None);
 
 
         self.set = Some(set);
     }
 
+    /// Makes sure wasm linear memory reaches the given number of bytes past the start pointer,
+    /// growing it by as many 64KiB pages as are missing. Returns false if the host refuses to grow it.
+    fn build_grow_memory(&mut self, ctx: &CompilationContext<'ctx>) {
+        let i32_t = ctx.context.i32_type();
+        let i64_t = ctx.context.i64_type();
+        let i8_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
+        let bool_t = ctx.context.bool_type();
+
+        let memory_size = ctx.module.add_function("llvm.wasm.memory.size.i32", i32_t.fn_type(&[i32_t.into()], false), None);
+        let memory_grow = ctx.module.add_function("llvm.wasm.memory.grow.i32", i32_t.fn_type(&[i32_t.into(), i32_t.into()], false), None);
+
+        let fn_type = bool_t.fn_type(&[i8_ptr_t.into(), i64_t.into()], false);
+        let grow_memory = ctx.module.add_function("grow_memory", fn_type, None);
+
+        let entry = ctx.context.append_basic_block(grow_memory, "entry");
+        let do_grow = ctx.context.append_basic_block(grow_memory, "do_grow");
+        let large_enough = ctx.context.append_basic_block(grow_memory, "large_enough");
+        ctx.builder.position_at_end(entry);
+
+        let start = grow_memory.get_nth_param(0).unwrap().into_pointer_value();
+        let bytes = grow_memory.get_nth_param(1).unwrap().into_int_value();
+
+        let val_0_32 = i32_t.const_int(0, false);
+        let page_size = i64_t.const_int(65536, false);
+        let start_addr = ctx.builder.build_ptr_to_int(start, i64_t, "start_addr");
+        let end_addr = ctx.builder.build_int_add(start_addr, bytes, "end_addr");
+        let pages = ctx.builder.build_call(memory_size, &[val_0_32.into()], "pages");
+        let pages = ctx.builder.build_int_z_extend(pages.as_any_value_enum().into_int_value(), i64_t, "pages_64");
+        let memory_end = ctx.builder.build_int_mul(pages, page_size, "memory_end");
+        let needs_grow = ctx.builder.build_int_compare(IntPredicate::UGT, end_addr, memory_end, "needs_grow");
+        ctx.builder.build_conditional_branch(needs_grow, do_grow, large_enough);
+
+        ctx.builder.position_at_end(do_grow);
+        let missing = ctx.builder.build_int_sub(end_addr, memory_end, "missing");
+        let rounded_up = ctx.builder.build_int_add(missing, i64_t.const_int(65535, false), "rounded_up");
+        let new_pages = ctx.builder.build_int_unsigned_div(rounded_up, page_size, "new_pages");
+        let new_pages = ctx.builder.build_int_truncate(new_pages, i32_t, "new_pages_32");
+        let old_pages = ctx.builder.build_call(memory_grow, &[val_0_32.into(), new_pages.into()], "old_pages");
+        let has_failed = ctx.builder.build_int_compare(IntPredicate::EQ, old_pages.as_any_value_enum().into_int_value(), i32_t.const_all_ones(), "has_failed");
+        let has_grown = ctx.builder.build_not(has_failed, "has_grown");
+        ctx.builder.build_return(Some(&has_grown));
+
+        ctx.builder.position_at_end(large_enough);
+        ctx.builder.build_return(Some(&bool_t.const_int(1, false)));
+
+
+        self.grow_memory = Some(grow_memory);
+    }
+
+    /// Prints the pointer and the cells within DEBUG_RADIUS of it to stderr, like the interpreter does for #.
+    /// Cells past the end of the tape are printed as zero without resizing it.
+    /// Relies on dprintf, which neither the C runtime on Windows nor wasm hosts provide.
+    fn build_debug_dump(&mut self, ctx: &CompilationContext<'ctx>) {
+        let i32_t = ctx.context.i32_type();
+        let i64_t = ctx.context.i64_type();
+        let i8_ptr_t = ctx.context.i8_type().ptr_type(AddressSpace::Generic);
+        let i64_ptr_t = i64_t.ptr_type(AddressSpace::Generic);
+        let cell_ptr_ptr_t = ctx.cell_ptr_type().ptr_type(AddressSpace::Generic);
+        let void_t = ctx.context.void_type();
+
+        let dprintf_type = i32_t.fn_type(&[i32_t.into(), i8_ptr_t.into()], true);
+        let dprintf = ctx.module.add_function("dprintf", dprintf_type, None);
+
+        let header = Self::build_const_str(ctx, "\nDebug on line %d, char %d: pointer %llu, cells from %llu:", "debug_header");
+        let cell = Self::build_const_str(ctx, " %llu", "debug_cell");
+        let current_cell = Self::build_const_str(ctx, " [%llu]", "debug_current_cell");
+        let newline = Self::build_const_str(ctx, "\n", "debug_newline");
+        let str_ptr = |global: GlobalValue<'ctx>, name: &str| {
+            ctx.builder.build_pointer_cast(global.as_pointer_value(), i8_ptr_t, name)
+        };
+
+
+        let fn_type = void_t.fn_type(&[cell_ptr_ptr_t.into(), i64_ptr_t.into(), i64_ptr_t.into(), i32_t.into(), i32_t.into()], false);
+        let debug_dump = ctx.module.add_function("debug_dump", fn_type, None);
+
+        let entry = ctx.context.append_basic_block(debug_dump, "entry");
+        let check = ctx.context.append_basic_block(debug_dump, "check");
+        let body = ctx.context.append_basic_block(debug_dump, "body");
+        let done = ctx.context.append_basic_block(debug_dump, "done");
+        ctx.builder.position_at_end(entry);
+
+        let array = debug_dump.get_nth_param(0).unwrap().into_pointer_value();
+        let len = debug_dump.get_nth_param(1).unwrap().into_pointer_value();
+        let index = debug_dump.get_nth_param(2).unwrap().into_pointer_value();
+        let line_val = debug_dump.get_nth_param(3).unwrap().into_int_value();
+        let char_val = debug_dump.get_nth_param(4).unwrap().into_int_value();
+
+
+        let stderr_fd = i32_t.const_int(2, false);
+        let val_0 = i64_t.const_int(0, false);
+        let val_1 = i64_t.const_int(1, false);
+        let radius = i64_t.const_int(DEBUG_RADIUS as u64, false);
+
+        let j = ctx.builder.build_alloca(i64_t, "j");
+        let len_val = ctx.builder.build_load(len, "len_val").into_int_value();
+        let i_val = ctx.builder.build_load(index, "index_val").into_int_value();
+        let arr_ptr = ctx.builder.build_load(array, "arr_ptr").into_pointer_value();
+
+        let has_room = ctx.builder.build_int_compare(IntPredicate::UGE, i_val, radius, "has_room");
+        let below = ctx.builder.build_int_sub(i_val, radius, "below");
+        let first = ctx.builder.build_select(has_room, below, val_0, "first").into_int_value();
+        let last = ctx.builder.build_int_add(i_val, radius, "last");
+
+        let header_ptr = str_ptr(header, "header");
+        ctx.builder.build_call(dprintf, &[stderr_fd.into(), header_ptr.into(), line_val.into(), char_val.into(), i_val.into(), first.into()], "");
+        ctx.builder.build_store(j, first);
+        ctx.builder.build_unconditional_branch(check);
+
+
+        ctx.builder.position_at_end(check);
+        let j_val = ctx.builder.build_load(j, "j_val").into_int_value();
+        let in_range = ctx.builder.build_int_compare(IntPredicate::ULE, j_val, last, "in_range");
+        ctx.builder.build_conditional_branch(in_range, body, done);
+
+
+        ctx.builder.position_at_end(body);
+        // Cells past the end of the tape are read from the first cell instead, and then replaced with the fill value.
+        let in_tape = ctx.builder.build_int_compare(IntPredicate::ULT, j_val, len_val, "in_tape");
+        let safe_j = ctx.builder.build_select(in_tape, j_val, val_0, "safe_j").into_int_value();
+        let cell_ptr = unsafe { ctx.builder.build_gep(arr_ptr, &[safe_j], "cell_ptr") };
+        let cell_val = ctx.builder.build_load(cell_ptr, "cell_val").into_int_value();
+        let cell_val = ctx.builder.build_int_z_extend(cell_val, i64_t, "cell_val_64");
+        let val_fill = i64_t.const_int(ctx.options.cell_width.fill(ctx.options.init_cell), false);
+        let cell_val = ctx.builder.build_select(in_tape, cell_val, val_fill, "shown_val");
+        let is_current = ctx.builder.build_int_compare(IntPredicate::EQ, j_val, i_val, "is_current");
+        let cell_fmt = str_ptr(cell, "cell_fmt");
+        let current_fmt = str_ptr(current_cell, "current_fmt");
+        let fmt = ctx.builder.build_select(is_current, current_fmt, cell_fmt, "fmt");
+        ctx.builder.build_call(dprintf, &[stderr_fd.into(), fmt.into(), cell_val.into()], "");
+        let next_j = ctx.builder.build_int_add(j_val, val_1, "next_j");
+        ctx.builder.build_store(j, next_j);
+        ctx.builder.build_unconditional_branch(check);
+
+
+        ctx.builder.position_at_end(done);
+        let newline_ptr = str_ptr(newline, "newline");
+        ctx.builder.build_call(dprintf, &[stderr_fd.into(), newline_ptr.into()], "");
+        ctx.builder.build_return(None);
+
+
+        self.debug_dump = Some(debug_dump);
+    }
+
     fn build_const_str(ctx: &CompilationContext<'ctx>, val: &str, name: &str) -> GlobalValue<'ctx> {
         let string = Self::str_to_bytes(val, ctx);
 
@@ -710,7 +1738,7 @@ struct Variables<'ctx> {
 }
 impl<'ctx> Variables<'ctx> {
     pub fn new(ctx: &CompilationContext<'ctx>, _symbols: &Symbols) -> Variables<'ctx> {
-        let array = ctx.builder.build_alloca(ctx.context.i8_type().ptr_type(AddressSpace::Generic), "arr");
+        let array = ctx.builder.build_alloca(ctx.cell_ptr_type(), "arr");
         let len = ctx.builder.build_alloca(ctx.context.i64_type(), "len");
         let index = ctx.builder.build_alloca(ctx.context.i64_type(), "index");
 
@@ -721,3 +1749,138 @@ impl<'ctx> Variables<'ctx> {
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::front_end::lexer::lex;
+    use crate::front_end::parser::parse;
+    use crate::optimizer::Optimizer;
+    use crate::interpreter::{interpret_with_tape, InterpreterOptions, SliceSource, VecWriter};
+    use crate::optimizer::apply_default_optimizations;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::cell::RefCell;
+
+    fn collapsed(src: &str) -> InstructionNode {
+        let mut program = parse(&lex(src)).unwrap();
+        Optimizer::from_names(vec!["collapse_increments", "collapse_decrements"]).unwrap().apply(&mut program);
+        program
+    }
+
+    /// Runs the program and returns the value of the cell it ends on.
+    fn final_cell(program: &InstructionNode, options: &CodegenOptions) -> i32 {
+        Target::initialize_native(&InitializationConfig::default()).unwrap();
+        let options = CodegenOptions { exit_from_cell: true, ..*options };
+        jit_run(program, "test", &options).unwrap()
+    }
+
+    thread_local! {
+        static OUTPUT: RefCell<Vec<u8>> = RefCell::new(Vec::new());
+    }
+    extern "C" fn capturing_putchar(c: i32) -> i32 {
+        OUTPUT.with(|output| output.borrow_mut().push(c as u8));
+        c
+    }
+
+    /// Runs the program and returns everything it wrote.
+    fn jit_output(program: &InstructionNode, options: &CodegenOptions) -> Vec<u8> {
+        Target::initialize_native(&InitializationConfig::default()).unwrap();
+        let context = Context::create();
+        let ctx = CompilationContext::new("test", &context, options, EntryPoint::Library);
+        build_module(&ctx, program);
+        let engine = ctx.module.create_jit_execution_engine(OptimizationLevel::None).unwrap();
+        engine.add_global_mapping(&ctx.module.get_function("putchar").unwrap(), capturing_putchar as usize);
+        let run = unsafe { engine.get_function::<unsafe extern "C" fn() -> i32>("bf_run") }.unwrap();
+
+        OUTPUT.with(|output| output.borrow_mut().clear());
+        assert_eq!(unsafe { run.call() }, 0);
+        OUTPUT.with(|output| output.borrow_mut().split_off(0))
+    }
+
+    #[test]
+    fn increment_runs_wrap_at_cell_width() {
+        for &(amount, cell) in &[(255, 255), (256, 0)] {
+            let program = collapsed(&format!("{}.", "+".repeat(amount)));
+            assert_eq!(jit_output(&program, &CodegenOptions::default()), vec![cell]);
+        }
+    }
+
+    #[test]
+    fn interpreter_and_compiler_agree_on_long_runs() {
+        for &(src, amount, cell) in &[("+", 256, 0), ("+", 511, 255), ("-", 256, 0), ("-", 511, 1)] {
+            let program = collapsed(&src.repeat(amount));
+
+            let mut out = VecWriter(Vec::new());
+            let (tape, result) = interpret_with_tape::<u8, _, _>(&program, &mut out, &mut SliceSource::new(Vec::new()), &InterpreterOptions::default());
+            result.unwrap();
+
+            assert_eq!(tape.current_cell() as i32, cell, "{} x {}", src, amount);
+            assert_eq!(final_cell(&program, &CodegenOptions::default()), cell, "{} x {}", src, amount);
+        }
+    }
+
+    #[test]
+    fn jit_run_reports_pointer_underflow() {
+        let program = parse(&lex("+<")).unwrap();
+        Target::initialize_native(&InitializationConfig::default()).unwrap();
+        assert!(jit_run(&program, "test", &CodegenOptions::default()).is_err());
+    }
+
+    static MALLOC_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" {
+        #[link_name = "malloc"]
+        fn libc_malloc(size: u64) -> *mut u8;
+    }
+    extern "C" fn counting_malloc(size: u64) -> *mut u8 {
+        MALLOC_CALLS.fetch_add(1, Ordering::SeqCst);
+        unsafe { libc_malloc(size) }
+    }
+
+    #[test]
+    fn walking_right_resizes_logarithmically() {
+        // Walks 100000 cells right, touching every one of them, with the copy loop as a single MoveAdd per step.
+        let mut program = parse(&lex(&format!("{}[[->+<]>-]", "+".repeat(100_000)))).unwrap();
+        apply_default_optimizations(&mut program);
+        let options = CodegenOptions { cell_width: CellWidth::Bits32, tape_size: 1, ..CodegenOptions::default() };
+
+        Target::initialize_native(&InitializationConfig::default()).unwrap();
+        let context = Context::create();
+        let ctx = CompilationContext::new("test", &context, &options, EntryPoint::Main);
+        build_module(&ctx, &program);
+        let engine = ctx.module.create_jit_execution_engine(OptimizationLevel::None).unwrap();
+        engine.add_global_mapping(&ctx.module.get_function("malloc").unwrap(), counting_malloc as usize);
+        let main = unsafe { engine.get_function::<unsafe extern "C" fn() -> i32>("main") }.unwrap();
+        unsafe { main.call() };
+
+        // Doubling from a single cell takes 17 resizes to reach 100001 cells, where growing by a fixed amount would take thousands.
+        let calls = MALLOC_CALLS.load(Ordering::SeqCst);
+        assert!(calls <= 20, "{} allocations", calls);
+    }
+
+    /// Only allocates the initial single cell tape, so growing it always fails.
+    extern "C" fn single_byte_malloc(size: u64) -> *mut u8 {
+        if size > 1 {
+            std::ptr::null_mut()
+        }
+        else {
+            unsafe { libc_malloc(size) }
+        }
+    }
+
+    #[test]
+    fn failing_resize_returns_from_library() {
+        let program = parse(&lex(">+")).unwrap();
+        let options = CodegenOptions { tape_size: 1, ..CodegenOptions::default() };
+
+        Target::initialize_native(&InitializationConfig::default()).unwrap();
+        let context = Context::create();
+        let ctx = CompilationContext::new("test", &context, &options, EntryPoint::Library);
+        build_module(&ctx, &program);
+        let engine = ctx.module.create_jit_execution_engine(OptimizationLevel::None).unwrap();
+        engine.add_global_mapping(&ctx.module.get_function("malloc").unwrap(), single_byte_malloc as usize);
+        let run = unsafe { engine.get_function::<unsafe extern "C" fn() -> i32>("bf_run") }.unwrap();
+        assert_eq!(unsafe { run.call() }, -1);
+    }
+}
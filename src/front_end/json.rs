@@ -0,0 +1,14 @@
+use crate::front_end::parser::InstructionNode;
+
+
+
+
+/// Reads a syntax tree written by to_json, or produced by any other tool using the same format.
+/// The tree is taken as is; loops and programs are not checked for being where the parser would put them.
+pub fn parse_json(json: &str) -> serde_json::Result<InstructionNode> {
+    serde_json::from_str(json)
+}
+
+pub fn to_json(node: &InstructionNode) -> String {
+    serde_json::to_string_pretty(node).expect("Syntax trees can always be serialized")
+}
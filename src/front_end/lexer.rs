@@ -1,4 +1,5 @@
-use std::io::Write;
+use std::io::{self, Write, Read, BufRead, BufReader};
+use std::collections::VecDeque;
 
 
 #[derive(Copy, Clone, Debug)]
@@ -11,17 +12,114 @@ pub enum TokenType {
     Input,
     BeginLoop,
     EndLoop,
+    Debug,
 }
 #[derive(Copy, Clone, Debug)]
 pub struct Token {
     pub token_type: TokenType,
     pub line: u32,
     pub char: u32,
+    /// The position of the token in the source, in bytes rather than chars.
+    pub byte_offset: usize,
+    /// The length of the token's char in bytes, which is only more than 1 with a remapped CharMap.
+    pub byte_len: usize,
 }
 
+/// Settings for how the lexer recognizes commands and counts positions.
+#[derive(Copy, Clone, Debug)]
+pub struct LexerOptions {
+    /// The chars standing for the eight commands.
+    pub char_map: CharMap,
+    /// The distance between tab stops, which a tab advances the char position to,
+    /// so positions line up with what editors show.
+    /// A width of 1 counts tabs like any other char.
+    pub tab_width: u32,
+    /// Whether # is the debug command, instead of being part of a comment.
+    pub debug_commands: bool,
+}
+impl Default for LexerOptions {
+    fn default() -> Self {
+        LexerOptions {
+            char_map: CharMap::standard(),
+            tab_width: 8,
+            debug_commands: false,
+        }
+    }
+}
+
+/// Which char stands for each of the eight commands, so dialects that only swap out
+/// the command chars can be lexed into the same tokens as brainfuck.
+/// If a char is mapped to more than one command, the first of them in the order of the fields wins.
+/// Newlines and tabs are always whitespace, even if they are mapped to a command.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CharMap {
+    pub next: char,
+    pub previous: char,
+    pub increment: char,
+    pub decrement: char,
+    pub output: char,
+    pub input: char,
+    pub begin_loop: char,
+    pub end_loop: char,
+}
+impl CharMap {
+    /// The commands of plain brainfuck, ><+-.,[]
+    pub fn standard() -> CharMap {
+        CharMap {
+            next: '>',
+            previous: '<',
+            increment: '+',
+            decrement: '-',
+            output: '.',
+            input: ',',
+            begin_loop: '[',
+            end_loop: ']',
+        }
+    }
+
+    pub fn token_type(&self, c: char) -> Option<TokenType> {
+        if c == self.next {
+            Some(TokenType::Next)
+        }
+        else if c == self.previous {
+            Some(TokenType::Previous)
+        }
+        else if c == self.increment {
+            Some(TokenType::Increment)
+        }
+        else if c == self.decrement {
+            Some(TokenType::Decrement)
+        }
+        else if c == self.output {
+            Some(TokenType::Output)
+        }
+        else if c == self.input {
+            Some(TokenType::Input)
+        }
+        else if c == self.begin_loop {
+            Some(TokenType::BeginLoop)
+        }
+        else if c == self.end_loop {
+            Some(TokenType::EndLoop)
+        }
+        else {
+            None
+        }
+    }
+}
+impl Default for CharMap {
+    fn default() -> Self {
+        CharMap::standard()
+    }
+}
+
+
 pub fn lex(src: &str) -> Vec<Token> {
-    let mut line = 1;
-    let mut char = 1;
+    lex_with_options(src, &LexerOptions::default())
+}
+
+pub fn lex_with_options(src: &str, options: &LexerOptions) -> Vec<Token> {
+    let mut state = LexState::new(options);
 
 
     let chars: Vec<_> = src.chars().collect();
@@ -29,71 +127,231 @@ pub fn lex(src: &str) -> Vec<Token> {
 
     let to_skip = get_shebang_length(&chars);
 
-    for c in src.chars().skip(to_skip) {
+    for (byte_offset, c) in src.char_indices().skip(to_skip) {
+        if let Some(token) = state.lex_char(c, byte_offset) {
+            tokens.push(token);
+        }
+    }
+
+
+    tokens
+}
+
+/// Splits a source file at its first !, into the program before it and the input for the program after it,
+/// which is how many online runners keep both in one file.
+/// The ! of a shebang line doesn't count. Without any !, the whole file is the program.
+pub fn split_input(src: &str) -> (&str, Option<&str>) {
+    let search_start = if src.starts_with("#!") {
+        src.find('\n').unwrap_or(src.len())
+    }
+    else {
+        0
+    };
+
+    match src[search_start..].find('!') {
+        Some(index) => {
+            let index = search_start + index;
+            (&src[..index], Some(&src[index + 1..]))
+        }
+        None => (src, None),
+    }
+}
+
+
+/// Lexes the program as it is read, so it never needs to be in memory as a whole.
+/// The tokens are the same lex would produce for the complete source, and can be parsed with parse_iter.
+/// Reading stops at the first error, which can then be retrieved with TokenReader::take_error.
+/// Bytes that aren't valid UTF-8 are treated as comments.
+pub fn lex_reader<R: Read>(reader: R) -> TokenReader<R> {
+    lex_reader_with_options(reader, &LexerOptions::default())
+}
+
+pub fn lex_reader_with_options<R: Read>(reader: R, options: &LexerOptions) -> TokenReader<R> {
+    TokenReader {
+        reader: BufReader::new(reader),
+        state: LexState::new(options),
+        byte_offset: 0,
+        pending: VecDeque::new(),
+        started: false,
+        error: None,
+    }
+}
+
+pub struct TokenReader<R: Read> {
+    reader: BufReader<R>,
+    state: LexState,
+    byte_offset: usize,
+    /// Chars already read while looking for a shebang, which still need to be lexed.
+    pending: VecDeque<(usize, char)>,
+    started: bool,
+    error: Option<io::Error>,
+}
+impl<R: Read> TokenReader<R> {
+    /// The error that ended reading early, if any.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    fn peek_byte(&mut self) -> Option<u8> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(buf) => return buf.first().copied(),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.error = Some(err);
+                    return None;
+                }
+            }
+        }
+    }
+    fn next_byte(&mut self) -> Option<u8> {
+        let byte = self.peek_byte()?;
+        self.reader.consume(1);
+        self.byte_offset += 1;
+        Some(byte)
+    }
+    fn read_char(&mut self) -> Option<(usize, char)> {
+        let offset = self.byte_offset;
+        let first = self.next_byte()?;
+
+        let width = match first {
+            0x00..=0x7F => return Some((offset, first as char)),
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+
+        // Continuation bytes are only consumed if they are ones, so a broken sequence can't swallow a command.
+        let mut bytes = vec![first];
+        while bytes.len() < width {
+            match self.peek_byte() {
+                Some(byte @ 0x80..=0xBF) => {
+                    bytes.push(byte);
+                    self.next_byte();
+                }
+                _ => break,
+            }
+        }
+
+        let c = std::str::from_utf8(&bytes).ok()
+            .and_then(|s| s.chars().next())
+            .unwrap_or('\u{FFFD}');
+        Some((offset, c))
+    }
+    fn next_char(&mut self) -> Option<(usize, char)> {
+        match self.pending.pop_front() {
+            Some(c) => Some(c),
+            None => self.read_char(),
+        }
+    }
+    /// Skips a #! line at the very start, like lex does.
+    fn skip_shebang(&mut self) {
+        let first = match self.read_char() {
+            Some(c) => c,
+            None => return,
+        };
+        self.pending.push_back(first);
+        if first.1 != '#' {
+            return;
+        }
+
+        let second = match self.read_char() {
+            Some(c) => c,
+            None => return,
+        };
+        self.pending.push_back(second);
+        if second.1 != '!' {
+            return;
+        }
+
+        self.pending.clear();
+        while let Some(c) = self.read_char() {
+            if c.1 == '\n' {
+                self.pending.push_back(c);
+                break;
+            }
+        }
+    }
+}
+impl<R: Read> Iterator for TokenReader<R> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if !self.started {
+            self.started = true;
+            self.skip_shebang();
+        }
+
+        while let Some((byte_offset, c)) = self.next_char() {
+            if let Some(token) = self.state.lex_char(c, byte_offset) {
+                return Some(token);
+            }
+        }
+
+        None
+    }
+}
+
+
+/// The position the lexer is at, shared by lex and lex_reader.
+struct LexState {
+    options: LexerOptions,
+    line: u32,
+    char: u32,
+}
+impl LexState {
+    fn new(options: &LexerOptions) -> LexState {
+        LexState {
+            options: *options,
+            line: 1,
+            char: 1,
+        }
+    }
+
+    /// Advances past the char, returning the token it stands for if it is a command.
+    fn lex_char(&mut self, c: char, byte_offset: usize) -> Option<Token> {
+        let tab_width = self.options.tab_width.max(1);
+
         match c {
             '\n' => {
-                line += 1;
-                char = 1;
-                continue;
+                self.line += 1;
+                self.char = 1;
+                None
             }
-            '\r' => (),
-
-            '>' => tokens.push(Token {
-                token_type: TokenType::Next,
-                line,
-                char,
-            }),
-            '<' => tokens.push(Token {
-                token_type: TokenType::Previous,
-                line,
-                char
-            }),
-            '+' => tokens.push(Token {
-                token_type: TokenType::Increment,
-                line,
-                char
-            }),
-            '-' => tokens.push(Token {
-                token_type: TokenType::Decrement,
-                line,
-                char,
-            }),
-            '.' => tokens.push(Token {
-                token_type: TokenType::Output,
-                line,
-                char,
-            }),
-            ',' => tokens.push(Token {
-                token_type: TokenType::Input,
-                line,
-                char
-            }),
-            '[' => tokens.push(Token {
-                token_type: TokenType::BeginLoop,
-                line,
-                char
-            }),
-            ']' => tokens.push(Token {
-                token_type: TokenType::EndLoop,
-                line,
-                char,
-            }),
-
-            _ => (),
-        }
-
-        char += 1;
-    }
+            '\t' => {
+                self.char = ((self.char - 1) / tab_width + 1) * tab_width + 1;
+                None
+            }
+            _ => {
+                let debug = if c == '#' && self.options.debug_commands {
+                    Some(TokenType::Debug)
+                }
+                else {
+                    None
+                };
+                let token_type = self.options.char_map.token_type(c).or(debug);
 
+                let token = token_type.map(|token_type| Token {
+                    token_type,
+                    line: self.line,
+                    char: self.char,
+                    byte_offset,
+                    byte_len: c.len_utf8(),
+                });
 
-    tokens
+                self.char += 1;
+                token
+            }
+        }
+    }
 }
 
 
 fn get_shebang_length(src: &[char]) -> usize {
     let mut len = 0;
 
-    if src.get(0) != Some(&'#') || src.get(1) != Some(&'!') {
+    if src.first() != Some(&'#') || src.get(1) != Some(&'!') {
         return 0;
     }
 
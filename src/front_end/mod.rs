@@ -1,3 +1,7 @@
 pub mod lexer;
 pub mod parser;
+#[cfg(feature = "serde")]
+mod json;
 
+#[cfg(feature = "serde")]
+pub use json::{parse_json, to_json};
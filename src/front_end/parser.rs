@@ -1,8 +1,10 @@
 use crate::front_end::lexer::{Token, TokenType};
 use std::fmt::{Display, Formatter};
 use std::io::Write;
+use std::iter::Peekable;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize, serde_crate::Deserialize), serde(crate = "serde_crate"))]
 pub enum NodeType {
     Program(Vec<InstructionNode>),
     Next(usize),
@@ -12,134 +14,370 @@ pub enum NodeType {
     Output,
     Input,
     Loop(Vec<InstructionNode>),
+    /// Prints the pointer and the cells around it, for debugging.
+    /// Only produced from # when the lexer has debug commands enabled.
+    Debug,
 
     // All following instructions are special-purpose for optimizing the above.
-    SetCell(usize)
+    SetCell(usize),
+    /// Adds the current cell, multiplied by a factor, to the cell at each offset.
+    /// Does nothing if the current cell is zero; the current cell itself is left unchanged.
+    MultiplyAdd { targets: Vec<(isize, i32)> },
+    /// Adds the current cell to the cell at the offset, leaving the current cell unchanged.
+    MoveAdd { offset: isize },
+    /// Moves the pointer right by the stride until it points to a zero cell.
+    ScanRight(usize),
+    /// Moves the pointer left by the stride until it points to a zero cell.
+    ScanLeft(usize),
+    /// Adds the amount to the cell at the offset, without moving the pointer.
+    AddAtOffset { offset: isize, amount: i32 },
+    /// Sets the cell at the offset to the value, without moving the pointer.
+    SetAtOffset { offset: isize, value: usize },
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize, serde_crate::Deserialize), serde(crate = "serde_crate"))]
 pub struct InstructionNode {
     pub node_type: NodeType,
     pub line: u32,
     pub char: u32,
+    /// Where in the source the node starts, in bytes.
+    pub byte_offset: usize,
+    /// One past the last byte of the source the node was made from.
+    /// For loops, this is just behind their closing bracket.
+    pub byte_end: usize,
+}
+impl InstructionNode {
+    /// A node that wasn't parsed from any source, like one of a tree built by hand to compare a parsed one against.
+    /// Its position is the start of the source, which doesn't matter for comparing.
+    pub fn new(node_type: NodeType) -> InstructionNode {
+        InstructionNode {
+            node_type,
+            line: 0,
+            char: 0,
+            byte_offset: 0,
+            byte_end: 0,
+        }
+    }
+    /// The part of the source the node was parsed from, comments in between included.
+    pub fn source_text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.byte_offset..self.byte_end]
+    }
+    /// The number of nodes in the tree, this one and all of its descendants included.
+    pub fn node_count(&self) -> usize {
+        metrics(self).node_count
+    }
+}
+/// Takes the tree apart one level at a time, so dropping deeply nested loops can't overflow the stack
+/// the way the recursive drop of the nested Vecs would.
+impl Drop for InstructionNode {
+    fn drop(&mut self) {
+        let mut nodes = match &mut self.node_type {
+            NodeType::Program(children) | NodeType::Loop(children) => std::mem::take(children),
+            _ => return,
+        };
+        while let Some(mut node) = nodes.pop() {
+            if let NodeType::Program(children) | NodeType::Loop(children) = &mut node.node_type {
+                nodes.append(children);
+            }
+        }
+    }
+}
+/// Nodes compare equal if they have the same structure,
+/// regardless of where in the source they came from.
+impl PartialEq for InstructionNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.node_type == other.node_type
+    }
 }
+impl Eq for InstructionNode {}
 
 
 
-pub fn parse(tokens: &[Token]) -> Result<InstructionNode, ParsingError> {
-    ParsingContext::new(tokens).parse_all()
+/// Settings for what the parser accepts.
+#[derive(Copy, Clone, Debug)]
+pub struct ParserOptions {
+    /// How many loops deep the program may nest.
+    /// The optimizer and compiler recurse into loops,
+    /// so without a limit, adversarially nested input could overflow the stack.
+    /// The default of 1000 is far deeper than real programs nest while staying well within the stack of those passes.
+    /// Parsing, interpreting and dropping the tree don't recurse, so unoptimized interpretation handles any depth the limit allows.
+    pub max_loop_depth: usize,
+}
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            max_loop_depth: 1000,
+        }
+    }
 }
 
-struct ParsingContext<'a> {
-    tokens: &'a [Token],
-    index: usize,
+
+/// Parses the whole program, reporting every unmatched bracket instead of stopping at the first one.
+/// Stray closing brackets are skipped and unclosed loops are ended at the end of the program,
+/// so the remaining brackets still pair up the way they would have otherwise.
+/// The errors are in the order they appear in the source.
+pub fn parse(tokens: &[Token]) -> Result<InstructionNode, Vec<ParsingError>> {
+    parse_with_options(tokens, &ParserOptions::default())
+}
+pub fn parse_with_options(tokens: &[Token], options: &ParserOptions) -> Result<InstructionNode, Vec<ParsingError>> {
+    parse_iter_with_options(tokens.iter().copied(), options)
+}
+
+/// Like parse, but takes the tokens one at a time, so they don't all need to be in memory at once.
+/// Meant to be used with lex_reader.
+pub fn parse_iter<I>(tokens: I) -> Result<InstructionNode, Vec<ParsingError>>
+    where I: IntoIterator<Item = Token>,
+{
+    parse_iter_with_options(tokens, &ParserOptions::default())
+}
+pub fn parse_iter_with_options<I>(tokens: I, options: &ParserOptions) -> Result<InstructionNode, Vec<ParsingError>>
+    where I: IntoIterator<Item = Token>,
+{
+    ParsingContext::new(tokens.into_iter(), options).parse_all()
+}
+
+/// Like parse, but only reports the first unmatched bracket.
+pub fn parse_first(tokens: &[Token]) -> Result<InstructionNode, ParsingError> {
+    parse(tokens).map_err(|errors| errors[0])
+}
+
+struct ParsingContext<I: Iterator<Item = Token>> {
+    tokens: Peekable<I>,
+    /// The byte just behind the last token consumed so far.
+    end_offset: usize,
+    errors: Vec<ParsingError>,
+    /// The loops the current token is in, innermost last.
+    /// Loops are kept on this stack instead of being parsed recursively,
+    /// so no nesting can overflow the native stack.
+    open_loops: Vec<OpenLoop>,
+    options: ParserOptions,
 }
-impl<'a> ParsingContext<'a> {
-    pub fn new(tokens: &'a [Token]) -> ParsingContext<'a> {
+/// A loop whose closing bracket hasn't been reached yet, with the nodes parsed inside of it so far.
+struct OpenLoop {
+    begin: Token,
+    children: Vec<InstructionNode>,
+}
+impl<I: Iterator<Item = Token>> ParsingContext<I> {
+    pub fn new(tokens: I, options: &ParserOptions) -> ParsingContext<I> {
         ParsingContext {
-            tokens,
-            index: 0,
+            tokens: tokens.peekable(),
+            end_offset: 0,
+            errors: Vec::new(),
+            open_loops: Vec::new(),
+            options: *options,
         }
     }
-    pub fn parse_all(mut self) -> Result<InstructionNode, ParsingError> {
-        let mut nodes = Vec::with_capacity(self.tokens.len());
+    pub fn parse_all(mut self) -> Result<InstructionNode, Vec<ParsingError>> {
+        let mut nodes = Vec::with_capacity(self.tokens.size_hint().0);
 
         while !self.is_end() {
-            nodes.push(self.parse_token()?);
+            if let Some(node) = self.parse_token() {
+                self.push_node(&mut nodes, node);
+            }
+        }
+
+        // Unclosed loops are ended at the end of the program.
+        while let Some(open) = self.open_loops.pop() {
+            self.errors.push(ParsingError::UnmatchedBeginLoop {
+                line: open.begin.line,
+                char: open.begin.char,
+                byte_offset: open.begin.byte_offset,
+            });
+            let node = loop_node(open, self.end_offset);
+            self.push_node(&mut nodes, node);
+        }
+
+        if !self.errors.is_empty() {
+            self.errors.sort_by_key(|err| err.position());
+            return Err(self.errors);
         }
 
 
         Ok(InstructionNode {
             node_type: NodeType::Program(nodes),
             line: 0,
-            char: 0
+            char: 0,
+            byte_offset: 0,
+            byte_end: self.end_offset,
         })
     }
+    /// Adds the node to the innermost open loop, or to the program if there is none.
+    fn push_node(&mut self, nodes: &mut Vec<InstructionNode>, node: InstructionNode) {
+        match self.open_loops.last_mut() {
+            Some(open) => open.children.push(node),
+            None => nodes.push(node),
+        }
+    }
 
 
-    fn current(&self) -> Token {
-        self.tokens[self.index]
+    fn current(&mut self) -> Token {
+        *self.tokens.peek().unwrap()
     }
-    fn is_end(&self) -> bool {
-        self.index >= self.tokens.len()
+    fn is_end(&mut self) -> bool {
+        self.tokens.peek().is_none()
+    }
+    fn advance(&mut self) {
+        if let Some(token) = self.tokens.next() {
+            self.end_offset = token.byte_offset + token.byte_len;
+        }
     }
 
-    fn construct_node(&self, n_type: NodeType) -> InstructionNode {
+    fn construct_node(&mut self, n_type: NodeType) -> InstructionNode {
+        let c = self.current();
+
         InstructionNode {
             node_type: n_type,
-            line: self.current().line,
-            char: self.current().char,
+            line: c.line,
+            char: c.char,
+            byte_offset: c.byte_offset,
+            byte_end: c.byte_offset + c.byte_len,
         }
     }
 
 
-    fn parse_token(&mut self) -> Result<InstructionNode, ParsingError> {
+    /// Parses the current token, returning the node it finishes, if any.
+    /// An opening bracket only starts a loop, which is finished by its closing bracket.
+    /// A stray ] is recorded as an error and skipped.
+    fn parse_token(&mut self) -> Option<InstructionNode> {
         let c = self.current();
 
-        let ret;
-
-        match c.token_type {
-            TokenType::Next => ret = self.construct_node(NodeType::Next(1)),
-            TokenType::Previous => ret = self.construct_node(NodeType::Previous(1)),
-            TokenType::Increment => ret = self.construct_node(NodeType::Increment(1)),
-            TokenType::Decrement => ret = self.construct_node(NodeType::Decrement(1)),
-            TokenType::Output => ret = self.construct_node(NodeType::Output),
-            TokenType::Input => ret = self.construct_node(NodeType::Input),
-            TokenType::BeginLoop => ret = self.parse_loop()?,
-            TokenType::EndLoop => return Err(ParsingError::UnmatchedEndLoop {
-                line: c.line,
-                char: c.char,
-            })
-        }
+        let ret = match c.token_type {
+            TokenType::Next => self.construct_node(NodeType::Next(1)),
+            TokenType::Previous => self.construct_node(NodeType::Previous(1)),
+            TokenType::Increment => self.construct_node(NodeType::Increment(1)),
+            TokenType::Decrement => self.construct_node(NodeType::Decrement(1)),
+            TokenType::Output => self.construct_node(NodeType::Output),
+            TokenType::Input => self.construct_node(NodeType::Input),
+            TokenType::Debug => self.construct_node(NodeType::Debug),
+            TokenType::BeginLoop if self.open_loops.len() >= self.options.max_loop_depth => {
+                self.errors.push(ParsingError::NestingTooDeep {
+                    line: c.line,
+                    char: c.char,
+                    byte_offset: c.byte_offset,
+                    depth: self.open_loops.len() + 1,
+                });
+                self.skip_loop();
+                return None;
+            }
+            TokenType::BeginLoop => {
+                self.open_loops.push(OpenLoop {
+                    begin: c,
+                    children: Vec::new(),
+                });
+                self.advance();
+                return None;
+            }
+            TokenType::EndLoop => match self.open_loops.pop() {
+                Some(open) => loop_node(open, c.byte_offset + c.byte_len),
+                None => {
+                    self.errors.push(ParsingError::UnmatchedEndLoop {
+                        line: c.line,
+                        char: c.char,
+                        byte_offset: c.byte_offset,
+                    });
+                    self.advance();
+                    return None;
+                }
+            }
+        };
 
-        self.index += 1;
+        self.advance();
 
-        Ok(ret)
+        Some(ret)
     }
-    fn parse_loop(&mut self) -> Result<InstructionNode, ParsingError> {
-        let begin_line = self.current().line;
-        let begin_char = self.current().char;
-
-        self.index += 1;
-
-        let mut children = Vec::new();
+    /// Skips the loop starting at the current token up to and including its closing bracket, or to the end,
+    /// without recursing into the loops nested in it.
+    fn skip_loop(&mut self) {
+        let mut depth = 0usize;
 
-        loop {
-            if self.is_end() {
-                return Err(ParsingError::UnmatchedBeginLoop { line: begin_line, char: begin_char})
+        while !self.is_end() {
+            match self.current().token_type {
+                TokenType::BeginLoop => depth += 1,
+                TokenType::EndLoop => depth -= 1,
+                _ => (),
             }
-            else if matches!(self.current().token_type, TokenType::EndLoop) {
+            self.advance();
+
+            if depth == 0 {
                 break;
             }
-            else {
-                children.push(self.parse_token()?);
-            }
         }
-
-
-        Ok(InstructionNode {
-            node_type: NodeType::Loop(children),
-            line: begin_line,
-            char: begin_char,
-        })
+    }
+}
+fn loop_node(open: OpenLoop, byte_end: usize) -> InstructionNode {
+    InstructionNode {
+        node_type: NodeType::Loop(open.children),
+        line: open.begin.line,
+        char: open.begin.char,
+        byte_offset: open.begin.byte_offset,
+        byte_end,
     }
 }
 
 
 #[derive(Copy, Clone, Debug)]
 pub enum ParsingError {
-    UnmatchedBeginLoop { line: u32, char: u32 },
-    UnmatchedEndLoop { line: u32, char: u32 },
+    UnmatchedBeginLoop { line: u32, char: u32, byte_offset: usize },
+    UnmatchedEndLoop { line: u32, char: u32, byte_offset: usize },
+    /// A loop nested deeper than ParserOptions allow. The loops inside of it are skipped.
+    NestingTooDeep { line: u32, char: u32, byte_offset: usize, depth: usize },
+}
+impl ParsingError {
+    /// The line and char of the offending bracket.
+    pub fn position(&self) -> (u32, u32) {
+        match *self {
+            Self::UnmatchedBeginLoop { line, char, .. } => (line, char),
+            Self::UnmatchedEndLoop { line, char, .. } => (line, char),
+            Self::NestingTooDeep { line, char, .. } => (line, char),
+        }
+    }
+    /// The position of the offending bracket in the source, in bytes.
+    pub fn byte_offset(&self) -> usize {
+        match *self {
+            Self::UnmatchedBeginLoop { byte_offset, .. } => byte_offset,
+            Self::UnmatchedEndLoop { byte_offset, .. } => byte_offset,
+            Self::NestingTooDeep { byte_offset, .. } => byte_offset,
+        }
+    }
+
+    /// The error message followed by the offending line of the source,
+    /// with a caret under the bracket the error is about.
+    pub fn render(&self, source: &str) -> String {
+        let (line, _) = self.position();
+        let offset = self.byte_offset();
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let text = source[line_start..].split('\n')
+            .next()
+            .unwrap_or("")
+            .trim_end_matches('\r');
+
+        // Tabs are kept in front of the caret so it lines up however wide the terminal draws them.
+        let padding: String = source[line_start..offset].chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let number = line.to_string();
+        let gutter = " ".repeat(number.len());
+
+        format!(
+            "{}\n{} |\n{} | {}\n{} | {}^",
+            self, gutter, number, text, gutter, padding,
+        )
+    }
 }
 impl Display for ParsingError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::UnmatchedBeginLoop { line, char} => {
+            Self::UnmatchedBeginLoop { line, char, .. } => {
                 write!(f, "Opening [ on line {}, char {} has no closing ]", line, char)
             }
-            Self::UnmatchedEndLoop { line, char } => {
+            Self::UnmatchedEndLoop { line, char, .. } => {
                 write!(f, "Closing ] on line {}, char {} has no opening [", line, char)
             }
+            Self::NestingTooDeep { line, char, depth, .. } => {
+                write!(f, "Opening [ on line {}, char {} nests loops {} deep, more than allowed", line, char, depth)
+            }
         }
     }
 }
@@ -176,6 +414,7 @@ pub fn print_tree<W: Write>(
         NodeType::Decrement(amount) => writeln!(out, "Decrement({})", amount)?,
         NodeType::Output => writeln!(out, "Output")?,
         NodeType::Input => writeln!(out, "Input")?,
+        NodeType::Debug => writeln!(out, "Debug")?,
         NodeType::Loop(nodes) => {
             writeln!(out, "Loop:")?;
 
@@ -202,7 +441,277 @@ pub fn print_tree<W: Write>(
         }
 
         NodeType::SetCell(amount) => writeln!(out, "SetCell({})", amount)?,
+        NodeType::MultiplyAdd { targets } => writeln!(out, "MultiplyAdd({:?})", targets)?,
+        NodeType::MoveAdd { offset } => writeln!(out, "MoveAdd({})", offset)?,
+        NodeType::ScanRight(stride) => writeln!(out, "ScanRight({})", stride)?,
+        NodeType::ScanLeft(stride) => writeln!(out, "ScanLeft({})", stride)?,
+        NodeType::AddAtOffset { offset, amount } => writeln!(out, "AddAtOffset({}, {})", offset, amount)?,
+        NodeType::SetAtOffset { offset, value } => writeln!(out, "SetAtOffset({}, {})", offset, value)?,
     }
 
     Ok(())
 }
+
+
+/// Writes the tree as a Graphviz digraph, with every loop drawn as a cluster around its body.
+/// Nodes are labeled with their type and source position;
+/// instructions produced by the optimizer are filled to set them apart from plain brainfuck.
+pub fn to_dot<W: Write>(node: &InstructionNode, out: &mut W) -> std::io::Result<()> {
+    writeln!(out, "digraph program {{")?;
+    writeln!(out, "    node [fontname=\"monospace\"];")?;
+
+    let mut next_id = 0;
+    write_dot_node(node, out, &mut next_id, "    ")?;
+
+    writeln!(out, "}}")
+}
+
+fn write_dot_node<W: Write>(
+    node: &InstructionNode,
+    out: &mut W,
+    next_id: &mut usize,
+    indent: &str,
+) -> std::io::Result<usize> {
+    let id = *next_id;
+    *next_id += 1;
+
+    let (shape, fill) = dot_style(&node.node_type);
+    let label = format!("{}\\n{}:{}", dot_label(&node.node_type), node.line, node.char);
+
+    let children = match &node.node_type {
+        NodeType::Program(nodes) | NodeType::Loop(nodes) => Some(nodes),
+        _ => None,
+    };
+
+    let child_indent = match &node.node_type {
+        NodeType::Loop(_) => {
+            writeln!(out, "{}subgraph cluster_{} {{", indent, id)?;
+            writeln!(out, "{}    style=dashed;", indent)?;
+            format!("{}    ", indent)
+        }
+        _ => indent.to_string(),
+    };
+
+    write!(out, "{}n{} [label=\"{}\", shape={}", child_indent, id, label, shape)?;
+    if let Some(color) = fill {
+        write!(out, ", style=filled, fillcolor={}", color)?;
+    }
+    writeln!(out, "];")?;
+
+    if let Some(nodes) = children {
+        for n in nodes {
+            let child = write_dot_node(n, out, next_id, &child_indent)?;
+            writeln!(out, "{}n{} -> n{};", child_indent, id, child)?;
+        }
+    }
+
+    if let NodeType::Loop(_) = &node.node_type {
+        writeln!(out, "{}}}", indent)?;
+    }
+
+    Ok(id)
+}
+
+fn dot_label(node_type: &NodeType) -> String {
+    match node_type {
+        NodeType::Program(_) => String::from("Program"),
+        NodeType::Next(amount) => format!("Next({})", amount),
+        NodeType::Previous(amount) => format!("Previous({})", amount),
+        NodeType::Increment(amount) => format!("Increment({})", amount),
+        NodeType::Decrement(amount) => format!("Decrement({})", amount),
+        NodeType::Output => String::from("Output"),
+        NodeType::Input => String::from("Input"),
+        NodeType::Debug => String::from("Debug"),
+        NodeType::Loop(_) => String::from("Loop"),
+        NodeType::SetCell(value) => format!("SetCell({})", value),
+        NodeType::MultiplyAdd { targets } => format!("MultiplyAdd({:?})", targets),
+        NodeType::MoveAdd { offset } => format!("MoveAdd({})", offset),
+        NodeType::ScanRight(stride) => format!("ScanRight({})", stride),
+        NodeType::ScanLeft(stride) => format!("ScanLeft({})", stride),
+        NodeType::AddAtOffset { offset, amount } => format!("AddAtOffset({}, {})", offset, amount),
+        NodeType::SetAtOffset { offset, value } => format!("SetAtOffset({}, {})", offset, value),
+    }
+}
+
+/// The shape and fill color of a node in the DOT output.
+fn dot_style(node_type: &NodeType) -> (&'static str, Option<&'static str>) {
+    match node_type {
+        NodeType::Program(_) => ("doubleoctagon", None),
+        NodeType::Loop(_) => ("ellipse", None),
+        NodeType::Next(_) | NodeType::Previous(_) => ("box", None),
+        NodeType::Increment(_) | NodeType::Decrement(_) => ("box", None),
+        NodeType::Output | NodeType::Input => ("parallelogram", None),
+        NodeType::Debug => ("note", Some("lightgray")),
+
+        NodeType::SetCell(_) => ("diamond", Some("lightblue")),
+        NodeType::SetAtOffset { .. } => ("diamond", Some("lightcyan")),
+        NodeType::AddAtOffset { .. } => ("box", Some("khaki")),
+        NodeType::MultiplyAdd { .. } => ("hexagon", Some("lightsalmon")),
+        NodeType::MoveAdd { .. } => ("hexagon", Some("lightpink")),
+        NodeType::ScanRight(_) | NodeType::ScanLeft(_) => ("cds", Some("palegreen")),
+    }
+}
+
+
+/// Numbers describing the size and shape of a tree.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AstMetrics {
+    /// All nodes, the root included.
+    pub node_count: usize,
+    /// How deep the loops nest, which is 0 for a program without loops.
+    pub max_depth: usize,
+    pub loop_count: usize,
+    /// The Input and Output nodes.
+    pub io_count: usize,
+}
+impl Display for AstMetrics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:>12} {}", "Nodes", self.node_count)?;
+        writeln!(f, "{:>12} {}", "Loops", self.loop_count)?;
+        writeln!(f, "{:>12} {}", "Max depth", self.max_depth)?;
+        write!(f, "{:>12} {}", "I/O", self.io_count)
+    }
+}
+
+pub fn metrics(node: &InstructionNode) -> AstMetrics {
+    let mut metrics = AstMetrics::default();
+
+    // The nodes still to count, with how many loops they are in, on a stack instead of recursing.
+    let mut pending = vec![(node, 0)];
+    while let Some((node, depth)) = pending.pop() {
+        metrics.node_count += 1;
+        metrics.max_depth = metrics.max_depth.max(depth);
+
+        match &node.node_type {
+            NodeType::Program(nodes) => pending.extend(nodes.iter().map(|n| (n, depth))),
+            NodeType::Loop(nodes) => {
+                metrics.loop_count += 1;
+                metrics.max_depth = metrics.max_depth.max(depth + 1);
+                pending.extend(nodes.iter().map(|n| (n, depth + 1)));
+            }
+            NodeType::Input | NodeType::Output => metrics.io_count += 1,
+            _ => (),
+        }
+    }
+
+    metrics
+}
+
+
+/// Turns the tree back into brainfuck source, so lex and parse followed by to_source
+/// reproduces the program without its comments.
+/// SetCell(v) is written as [-] followed by v +, or by -v - if v wrapped around below zero.
+/// MultiplyAdd and MoveAdd are written as the loops they were made from, which also clear the current cell;
+/// the optimizer always follows them with a SetCell, so the program still behaves the same.
+pub fn to_source(node: &InstructionNode) -> String {
+    let mut source = String::new();
+    write_source(&node.node_type, &mut source);
+    source
+}
+
+fn write_source(node_type: &NodeType, source: &mut String) {
+    match node_type {
+        NodeType::Program(nodes) => {
+            for n in nodes {
+                write_source(&n.node_type, source);
+            }
+        }
+        NodeType::Loop(nodes) => {
+            source.push('[');
+            for n in nodes {
+                write_source(&n.node_type, source);
+            }
+            source.push(']');
+        }
+        NodeType::Next(amount) => push_repeated(source, '>', *amount),
+        NodeType::Previous(amount) => push_repeated(source, '<', *amount),
+        NodeType::Increment(amount) => push_repeated(source, '+', *amount),
+        NodeType::Decrement(amount) => push_repeated(source, '-', *amount),
+        NodeType::Output => source.push('.'),
+        NodeType::Input => source.push(','),
+        NodeType::Debug => source.push('#'),
+
+        NodeType::SetCell(value) => push_set(source, *value),
+        NodeType::MultiplyAdd { targets } => {
+            source.push_str("[-");
+            for &(offset, factor) in targets {
+                push_move(source, offset);
+                push_add(source, factor as i64);
+                push_move(source, offset.wrapping_neg());
+            }
+            source.push(']');
+        }
+        NodeType::MoveAdd { offset } => {
+            source.push_str("[-");
+            push_move(source, *offset);
+            source.push('+');
+            push_move(source, offset.wrapping_neg());
+            source.push(']');
+        }
+        NodeType::ScanRight(stride) => {
+            source.push('[');
+            push_repeated(source, '>', *stride);
+            source.push(']');
+        }
+        NodeType::ScanLeft(stride) => {
+            source.push('[');
+            push_repeated(source, '<', *stride);
+            source.push(']');
+        }
+        NodeType::AddAtOffset { offset, amount } => {
+            push_move(source, *offset);
+            push_add(source, *amount as i64);
+            push_move(source, offset.wrapping_neg());
+        }
+        NodeType::SetAtOffset { offset, value } => {
+            push_move(source, *offset);
+            push_set(source, *value);
+            push_move(source, offset.wrapping_neg());
+        }
+    }
+}
+
+fn push_repeated(source: &mut String, c: char, amount: usize) {
+    source.push_str(&c.to_string().repeat(amount));
+}
+/// Cells are at most 32 bits wide, so only the lowest 32 bits of the value matter.
+/// Values in the upper half are written as decrements, so [-]- doesn't turn into four billion +.
+fn push_set(source: &mut String, value: usize) {
+    source.push_str("[-]");
+    push_add(source, value as u32 as i32 as i64);
+}
+fn push_move(source: &mut String, offset: isize) {
+    if offset < 0 {
+        push_repeated(source, '<', offset.wrapping_neg() as usize);
+    }
+    else {
+        push_repeated(source, '>', offset as usize);
+    }
+}
+fn push_add(source: &mut String, amount: i64) {
+    if amount < 0 {
+        push_repeated(source, '-', amount.wrapping_neg() as usize);
+    }
+    else {
+        push_repeated(source, '+', amount as usize);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_source_writes_wrapped_set_values_as_decrements() {
+        let program = InstructionNode::new(NodeType::Program(vec![
+            InstructionNode::new(NodeType::Input),
+            InstructionNode::new(NodeType::SetCell(usize::MAX)),
+            InstructionNode::new(NodeType::SetAtOffset { offset: 1, value: 3usize.wrapping_neg() }),
+            InstructionNode::new(NodeType::SetAtOffset { offset: -1, value: 2 }),
+            InstructionNode::new(NodeType::Output),
+        ]));
+
+        assert_eq!(to_source(&program), ",[-]->[-]---<<[-]++>.");
+    }
+}
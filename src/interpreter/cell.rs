@@ -0,0 +1,65 @@
+use std::fmt::Debug;
+
+
+
+
+/// A single cell on the tape of the interpreter.
+/// Arithmetic on cells wraps around at the width of the type.
+pub trait Cell: Copy + Default + PartialEq + Debug {
+    fn wrapping_add(self, other: Self) -> Self;
+
+    /// The largest value a cell can hold.
+    const MAX: u64;
+
+    /// Converts a value to a cell, keeping only as many of the low bits as fit.
+    fn from_u64(val: u64) -> Self;
+    fn to_u64(self) -> u64;
+
+    /// The byte written when outputting this cell; wider cells are truncated.
+    fn to_byte(self) -> u8 {
+        self.to_u64() as u8
+    }
+    /// The cell an input byte is stored as; wider cells are zero-extended.
+    fn from_byte(byte: u8) -> Self {
+        Self::from_u64(byte as u64)
+    }
+
+    fn is_zero(self) -> bool {
+        self == Self::default()
+    }
+
+    /// Adds the signed amount to the cell, or returns None if the result would wrap around.
+    fn checked_add_amount(self, amount: i128) -> Option<Self> {
+        let result = self.to_u64() as i128 + amount;
+        if result < 0 || result > Self::MAX as i128 {
+            None
+        }
+        else {
+            Some(Self::from_u64(result as u64))
+        }
+    }
+}
+
+
+macro_rules! impl_cell {
+    ($t: ty) => {
+        impl Cell for $t {
+            fn wrapping_add(self, other: Self) -> Self {
+                <$t>::wrapping_add(self, other)
+            }
+
+            const MAX: u64 = <$t>::MAX as u64;
+
+            fn from_u64(val: u64) -> Self {
+                val as $t
+            }
+            fn to_u64(self) -> u64 {
+                self as u64
+            }
+        }
+    };
+}
+
+impl_cell!(u8);
+impl_cell!(u16);
+impl_cell!(u32);
@@ -0,0 +1,110 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::interpreter::{Context, InterpreterHook, DEBUG_RADIUS};
+use crate::interpreter::cell::Cell;
+use std::collections::HashSet;
+use std::io::Write;
+
+
+
+
+/// An interactive debugger, pausing the interpreter to read commands from stdin.
+/// It starts out stepping, pausing before every node, and can run on to the next breakpoint instead.
+/// The commands are:
+///  - step, s or an empty line: execute the next node and pause again
+///  - continue or c: run until a breakpoint is reached
+///  - break N or b N: pause whenever execution reaches line N
+///  - print or p: print the tape around the pointer again
+///
+/// Since commands and program input both come from stdin, they can't be redirected separately.
+/// Once stdin runs out, the program runs on without pausing.
+pub struct Debugger {
+    stepping: bool,
+    breakpoints: HashSet<u32>,
+    last_line: u32,
+}
+impl Default for Debugger {
+    fn default() -> Self {
+        Debugger::new()
+    }
+}
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger {
+            stepping: true,
+            breakpoints: HashSet::new(),
+            last_line: 0,
+        }
+    }
+
+    /// Whether to pause before the node.
+    /// A breakpoint only pauses when execution arrives from another line,
+    /// so continuing doesn't stop again at every other node on the same line.
+    fn should_pause(&mut self, node: &InstructionNode) -> bool {
+        let entered_line = node.line != self.last_line;
+        self.last_line = node.line;
+
+        if let NodeType::Program(_) = node.node_type {
+            return false;
+        }
+
+        self.stepping || (entered_line && self.breakpoints.contains(&node.line))
+    }
+
+    fn pause<C: Cell>(&mut self, node: &InstructionNode, context: &Context<C>) {
+        eprintln!("\nLine {}, char {}: {}", node.line, node.char, describe_node(node));
+        eprintln!("{}", context.describe_tape(DEBUG_RADIUS));
+
+        loop {
+            eprint!("(debug) ");
+            std::io::stderr().flush().unwrap();
+
+            let mut command = String::new();
+            match std::io::stdin().read_line(&mut command) {
+                Ok(0) | Err(_) => {
+                    self.stepping = false;
+                    self.breakpoints.clear();
+                    return;
+                }
+                Ok(_) => (),
+            }
+
+            let mut words = command.split_whitespace();
+            match (words.next(), words.next()) {
+                (None, _) | (Some("step"), _) | (Some("s"), _) => {
+                    self.stepping = true;
+                    return;
+                }
+                (Some("continue"), _) | (Some("c"), _) => {
+                    self.stepping = false;
+                    return;
+                }
+                (Some("break"), Some(line)) | (Some("b"), Some(line)) => match line.parse::<u32>() {
+                    Ok(line) => {
+                        self.breakpoints.insert(line);
+                        eprintln!("Breakpoint on line {}", line);
+                    }
+                    Err(_) => eprintln!("Invalid line: {}", line),
+                },
+                (Some("break"), None) | (Some("b"), None) => eprintln!("Expected a line to break on"),
+                (Some("print"), _) | (Some("p"), _) => eprintln!("{}", context.describe_tape(DEBUG_RADIUS)),
+                (Some(other), _) => eprintln!("Unknown command: {} (expected step, continue, break <line> or print)", other),
+            }
+        }
+    }
+}
+impl<C: Cell> InterpreterHook<C> for Debugger {
+    fn before_node(&mut self, node: &InstructionNode, context: &Context<C>) {
+        if self.should_pause(node) {
+            self.pause(node, context);
+        }
+    }
+}
+
+
+/// The node's type without any of its children, which could be the whole rest of the program.
+fn describe_node(node: &InstructionNode) -> String {
+    match &node.node_type {
+        NodeType::Loop(children) => format!("Loop of {} nodes", children.len()),
+        node_type => format!("{:?}", node_type),
+    }
+}
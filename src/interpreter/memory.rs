@@ -0,0 +1,161 @@
+use crate::interpreter::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+
+
+
+
+/// The cells of the tape, indexed from the leftmost cell the program has reached.
+/// Cells that have never been touched hold the fill value, which is zero unless set otherwise.
+#[derive(Clone, Debug)]
+pub struct Memory<C: Cell> {
+    cells: Cells<C>,
+    fill: C,
+}
+#[derive(Clone, Debug)]
+enum Cells<C: Cell> {
+    /// All cells up to the highest one touched, which is fastest for the usual programs staying near the start.
+    Dense(Vec<C>),
+    /// Only the touched cells, so programs jumping far out along the tape don't need memory for everything in between,
+    /// at the cost of a lookup on every access.
+    Sparse(HashMap<usize, C>),
+}
+impl<C: Cell> Memory<C> {
+    pub(crate) fn dense(capacity: usize) -> Memory<C> {
+        Memory {
+            cells: Cells::Dense(Vec::with_capacity(capacity)),
+            fill: C::default(),
+        }
+    }
+    pub(crate) fn sparse() -> Memory<C> {
+        Memory {
+            cells: Cells::Sparse(HashMap::new()),
+            fill: C::default(),
+        }
+    }
+    /// Makes cells that have never been touched hold the value instead of zero.
+    pub(crate) fn with_fill(mut self, fill: C) -> Memory<C> {
+        self.fill = fill;
+        self
+    }
+    pub fn fill(&self) -> C {
+        self.fill
+    }
+
+    pub fn cell(&self, index: usize) -> C {
+        match &self.cells {
+            Cells::Dense(cells) => cells.get(index).copied().unwrap_or(self.fill),
+            Cells::Sparse(cells) => cells.get(&index).copied().unwrap_or(self.fill),
+        }
+    }
+    /// The cell at the index, which a dense memory has to have been expanded to include.
+    pub(crate) fn cell_mut(&mut self, index: usize) -> &mut C {
+        match &mut self.cells {
+            Cells::Dense(cells) => &mut cells[index],
+            Cells::Sparse(cells) => cells.entry(index).or_insert(self.fill),
+        }
+    }
+    /// Makes sure the cell at the index exists. Does nothing for a sparse memory, where every cell does.
+    pub(crate) fn expand_to(&mut self, index: usize) {
+        if let Cells::Dense(cells) = &mut self.cells {
+            while cells.len() <= index {
+                cells.push(self.fill);
+            }
+        }
+    }
+    /// One past the highest cell that has been touched.
+    pub fn len(&self) -> usize {
+        match &self.cells {
+            Cells::Dense(cells) => cells.len(),
+            Cells::Sparse(cells) => cells.keys().max().map(|&index| index + 1).unwrap_or(0),
+        }
+    }
+    /// Whether no cell has been touched yet.
+    pub fn is_empty(&self) -> bool {
+        match &self.cells {
+            Cells::Dense(cells) => cells.is_empty(),
+            Cells::Sparse(cells) => cells.is_empty(),
+        }
+    }
+    /// How many of the cells up to the highest one touched hold each value.
+    pub fn histogram(&self) -> BTreeMap<u64, usize> {
+        let mut histogram = BTreeMap::new();
+        match &self.cells {
+            Cells::Dense(cells) => {
+                for cell in cells {
+                    *histogram.entry(cell.to_u64()).or_insert(0) += 1;
+                }
+            }
+            Cells::Sparse(cells) => {
+                for cell in cells.values() {
+                    *histogram.entry(cell.to_u64()).or_insert(0) += 1;
+                }
+                let untouched = self.len() - cells.len();
+                if untouched != 0 {
+                    *histogram.entry(self.fill.to_u64()).or_insert(0) += untouched;
+                }
+            }
+        }
+        histogram
+    }
+    /// Adds the number of untouched cells in front of the first one, moving all others to the right.
+    pub(crate) fn prepend(&mut self, count: usize) {
+        match &mut self.cells {
+            Cells::Dense(cells) => {
+                let mut new_cells = vec![self.fill; count];
+                new_cells.extend_from_slice(cells);
+                *cells = new_cells;
+            }
+            Cells::Sparse(cells) => {
+                *cells = cells.drain()
+                    .map(|(index, cell)| (index + count, cell))
+                    .collect();
+            }
+        }
+    }
+
+    /// The number of strides from the start to the first zero cell to the right of it.
+    /// With a zero fill there always is one, since all cells past the ones touched are zero.
+    /// Otherwise, this is None if there is no zero cell before the end of memory.
+    pub(crate) fn scan_right(&self, start: usize, stride: usize) -> Option<usize> {
+        match &self.cells {
+            Cells::Dense(cells) => {
+                let len = cells.len();
+                let found = cells[start.min(len)..].iter()
+                    .step_by(stride)
+                    .position(|cell| cell.is_zero());
+
+                // Cells past the end of memory are all zero with a zero fill, so stop at the first one of those.
+                match found {
+                    Some(steps) => Some(steps),
+                    None if self.fill.is_zero() => Some(len.saturating_sub(start).div_ceil(stride)),
+                    None => None,
+                }
+            }
+            Cells::Sparse(_) => {
+                let len = self.len();
+                let mut steps = 0;
+                loop {
+                    let index = start + steps * stride;
+                    if self.cell(index).is_zero() {
+                        return Some(steps);
+                    }
+                    if index >= len {
+                        return None;
+                    }
+                    steps += 1;
+                }
+            }
+        }
+    }
+    /// The number of strides from the start to the first zero cell to the left of it,
+    /// if there is one before the start of memory.
+    pub(crate) fn scan_left(&self, start: usize, stride: usize) -> Option<usize> {
+        match &self.cells {
+            Cells::Dense(cells) => cells[..=start].iter()
+                .rev()
+                .step_by(stride)
+                .position(|cell| cell.is_zero()),
+            Cells::Sparse(_) => (0..=start / stride).find(|steps| self.cell(start - steps * stride).is_zero()),
+        }
+    }
+}
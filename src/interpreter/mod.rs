@@ -1,7 +1,19 @@
 use crate::front_end::parser::{InstructionNode, NodeType};
-use std::io::{Read, Write, ErrorKind};
+use std::io::{Read, Write, BufReader, BufWriter, Stdout, ErrorKind};
+use std::fs::File;
+use std::path::Path;
 use std::fmt::{Display, Formatter};
 use std::time::Duration;
+use std::str::FromStr;
+pub use cell::Cell;
+pub use profile::Profile;
+pub use debugger::Debugger;
+pub use memory::Memory;
+
+mod cell;
+mod profile;
+mod debugger;
+mod memory;
 
 
 pub trait ByteSource {
@@ -15,6 +27,7 @@ impl ByteSource for StdInSource {
         let result = std::io::stdin().lock().read(&mut buf);
 
         match result {
+            Ok(0) => None,
             Ok(_size) => Some(buf[0]),
             Err(err) => {
                 let kind = err.kind();
@@ -28,8 +41,105 @@ impl ByteSource for StdInSource {
     }
 }
 
+/// Reads all of stdin the first time input is needed, and serves the following reads from memory.
+/// Much faster than StdInSource for programs reading a lot of input,
+/// but programs can't react to input before all of it has been given.
+pub struct BufferedStdInSource {
+    input: Option<SliceSource>,
+}
+impl Default for BufferedStdInSource {
+    fn default() -> Self {
+        BufferedStdInSource::new()
+    }
+}
+impl BufferedStdInSource {
+    pub fn new() -> BufferedStdInSource {
+        BufferedStdInSource {
+            input: None,
+        }
+    }
+}
+impl ByteSource for BufferedStdInSource {
+    fn read(&mut self) -> Option<u8> {
+        let input = self.input.get_or_insert_with(|| {
+            let mut data = Vec::new();
+            if let Err(err) = std::io::stdin().lock().read_to_end(&mut data) {
+                panic!("Failed to read input: {:?}", err.kind());
+            }
+            SliceSource::new(data)
+        });
+
+        input.read()
+    }
+}
+/// Reads input from a file through a buffer, so it doesn't have to fit in memory.
+/// Once the end of the file is reached, read returns None, so the EOF policy applies.
+pub struct FileSource {
+    reader: BufReader<File>,
+}
+impl FileSource {
+    pub fn open(path: &Path) -> std::io::Result<FileSource> {
+        Ok(FileSource {
+            reader: BufReader::new(File::open(path)?),
+        })
+    }
+}
+impl ByteSource for FileSource {
+    fn read(&mut self) -> Option<u8> {
+        let mut buf = [0];
+
+        match self.reader.read(&mut buf) {
+            Ok(0) => None,
+            Ok(_size) => Some(buf[0]),
+            Err(err) => panic!("Failed to read input file: {:?}", err.kind()),
+        }
+    }
+}
+/// Turns the \r\n and lone \r line endings of the input from another source into \n,
+/// so programs written for one platform's line endings can read input from another.
+/// After a \r, the following byte has to be read to know whether it is a \n,
+/// so with interactive input, the \r only reaches the program once the next byte has been typed.
+pub struct NormalizeNewlines<S: ByteSource> {
+    source: S,
+    /// The byte read after a lone \r, which is returned next.
+    pending: Option<u8>,
+}
+impl<S: ByteSource> NormalizeNewlines<S> {
+    pub fn new(source: S) -> NormalizeNewlines<S> {
+        NormalizeNewlines {
+            source,
+            pending: None,
+        }
+    }
+}
+impl<S: ByteSource> ByteSource for NormalizeNewlines<S> {
+    fn read(&mut self) -> Option<u8> {
+        let byte = match self.pending.take() {
+            Some(byte) => byte,
+            None => self.source.read()?,
+        };
+        if byte != b'\r' {
+            return Some(byte);
+        }
+
+        match self.source.read() {
+            Some(b'\n') | None => (),
+            next => self.pending = next,
+        }
+        Some(b'\n')
+    }
+}
+impl<S: ByteSource + ?Sized> ByteSource for Box<S> {
+    fn read(&mut self) -> Option<u8> {
+        (**self).read()
+    }
+}
+
 pub trait ByteWriter {
     fn write(&mut self, val: u8);
+    /// Makes sure everything written so far is visible.
+    /// The interpreter calls this before reading input, so prompts show up before the program waits.
+    fn flush(&mut self) {}
 }
 pub struct StdOutWriter;
 impl ByteWriter for StdOutWriter {
@@ -40,34 +150,457 @@ impl ByteWriter for StdOutWriter {
     }
 }
 
+/// Collects output in a buffer and only writes it to stdout when flushed or dropped,
+/// or when the buffer fills up.
+/// Much faster than StdOutWriter for programs writing a lot of output.
+pub struct BufferedStdOutWriter {
+    out: BufWriter<Stdout>,
+}
+impl Default for BufferedStdOutWriter {
+    fn default() -> Self {
+        BufferedStdOutWriter::new()
+    }
+}
+impl BufferedStdOutWriter {
+    pub fn new() -> BufferedStdOutWriter {
+        BufferedStdOutWriter {
+            out: BufWriter::new(std::io::stdout()),
+        }
+    }
+}
+impl ByteWriter for BufferedStdOutWriter {
+    fn write(&mut self, val: u8) {
+        self.out.write_all(&[val]).unwrap();
+    }
+    fn flush(&mut self) {
+        self.out.flush().unwrap();
+    }
+}
+/// Writes output to a file through a buffer, which is flushed when flush is called or the writer is dropped.
+pub struct FileWriter {
+    out: BufWriter<File>,
+}
+impl FileWriter {
+    /// Creates the file, or truncates it if it already exists.
+    pub fn create(path: &Path) -> std::io::Result<FileWriter> {
+        Ok(FileWriter {
+            out: BufWriter::new(File::create(path)?),
+        })
+    }
+}
+impl ByteWriter for FileWriter {
+    fn write(&mut self, val: u8) {
+        self.out.write_all(&[val]).unwrap();
+    }
+    fn flush(&mut self) {
+        self.out.flush().unwrap();
+    }
+}
+impl<W: ByteWriter + ?Sized> ByteWriter for Box<W> {
+    fn write(&mut self, val: u8) {
+        (**self).write(val)
+    }
+    fn flush(&mut self) {
+        (**self).flush()
+    }
+}
+
+/// Reads input from a fixed buffer of bytes.
+/// Once all bytes are consumed, read returns None, so the EOF policy applies.
+pub struct SliceSource {
+    pub data: Vec<u8>,
+    pub pos: usize,
+}
+impl SliceSource {
+    pub fn new(data: Vec<u8>) -> SliceSource {
+        SliceSource {
+            data,
+            pos: 0,
+        }
+    }
+}
+impl ByteSource for SliceSource {
+    fn read(&mut self) -> Option<u8> {
+        let val = self.data.get(self.pos).copied();
+        if val.is_some() {
+            self.pos += 1;
+        }
+        val
+    }
+}
+
+/// Collects all output in memory.
+pub struct VecWriter(pub Vec<u8>);
+impl ByteWriter for VecWriter {
+    fn write(&mut self, val: u8) {
+        self.0.push(val);
+    }
+}
+
+
+
+
+/// What an input instruction does to the current cell once the input is exhausted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EofPolicy {
+    /// Leave the cell as it is.
+    Unchanged,
+    /// Set the cell to zero.
+    Zero,
+    /// Set the cell to -1, meaning all bits are set.
+    NegativeOne,
+}
+impl FromStr for EofPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unchanged" => Ok(Self::Unchanged),
+            "zero" | "0" => Ok(Self::Zero),
+            "negative-one" | "-1" => Ok(Self::NegativeOne),
+            _ => Err(format!("Invalid EOF policy: {} (expected unchanged, zero or -1)", s)),
+        }
+    }
+}
+
+
+/// How far the tape extends to the right.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TapeBounds {
+    /// The tape grows as needed, until memory runs out.
+    Growable,
+    /// The tape has exactly this many cells, and accessing any past them is an error.
+    Fixed(usize),
+}
 
+/// What happens when the pointer moves off either end of the tape.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PointerMode {
+    /// The tape is a line, so moving off the end grows it or is an error, depending on the TapeBounds and bidirectional options.
+    Linear,
+    /// The tape is a circle, so moving off one end comes back in at the other.
+    /// Only takes effect with a fixed tape, since a growable one has no other end to come back in at.
+    Wrap,
+}
 
 
-pub fn interpret<R, W>(node: &InstructionNode, out: &mut W, src: &mut R, sleep: Option<u32>) -> InterpretationResult
-    where R: ByteSource,
+#[derive(Copy, Clone, Debug)]
+pub struct InterpreterOptions {
+    /// The amount of time to sleep after each instruction, in milliseconds.
+    pub sleep: Option<u32>,
+    pub eof_policy: EofPolicy,
+    /// The maximum number of instructions to execute before giving up.
+    /// Every node of the optimized tree counts as one step,
+    /// so a collapsed Increment(5) is a single step, as is every loop iteration.
+    pub max_steps: Option<u64>,
+    /// The number of cells to reserve memory for up front.
+    pub tape_size: usize,
+    pub tape_bounds: TapeBounds,
+    /// Let the tape extend to the left of the starting cell when the pointer moves there,
+    /// instead of failing with a pointer underflow.
+    pub bidirectional: bool,
+    /// Only store the cells the program touches, instead of every cell up to the highest one.
+    /// Saves memory for programs moving far out along the tape, but makes every access slower.
+    pub sparse_tape: bool,
+    pub pointer_mode: PointerMode,
+    /// Fail with a cell overflow whenever arithmetic would take a cell below zero or past its maximum,
+    /// instead of wrapping around. Breaks programs that wrap on purpose, like ones using `-` on a zero cell to get the maximum value.
+    /// Arithmetic the optimizer already did isn't checked, so wraps in merged runs of `+` and `-`
+    /// or in the part of the program before the first input can go unnoticed.
+    pub strict_cells: bool,
+    /// The byte every byte of a fresh cell is set to, like memset would, so a 16 bit cell starts at 0x0101 for 1.
+    /// Anything but zero helps catch programs reading cells they never wrote,
+    /// but also means scans only stop at cells the program zeroed itself.
+    pub init_cell: u8,
+}
+impl Default for InterpreterOptions {
+    fn default() -> Self {
+        InterpreterOptions {
+            sleep: None,
+            eof_policy: EofPolicy::Unchanged,
+            max_steps: None,
+            tape_size: 30000,
+            tape_bounds: TapeBounds::Growable,
+            bidirectional: false,
+            sparse_tape: false,
+            pointer_mode: PointerMode::Linear,
+            strict_cells: false,
+            init_cell: 0,
+        }
+    }
+}
+
+
+/// Interprets a program on a tape of cells of type C,
+/// which can be any of u8, u16 or u32.
+pub fn interpret<C, R, W>(node: &InstructionNode, out: &mut W, src: &mut R, options: &InterpreterOptions) -> InterpretationResult
+    where C: Cell,
+          R: ByteSource,
           W: ByteWriter, {
-    let mut context = Context {
-        memory: Vec::with_capacity(30000),
-        p: 0,
-    };
+    interpret_with_tape::<C, R, W>(node, out, src, options).1
+}
+
+/// Interprets a program like interpret does, and also returns the tape as it was when execution stopped.
+/// If execution failed, the tape is the one at the point of failure.
+pub fn interpret_with_tape<C, R, W>(node: &InstructionNode, out: &mut W, src: &mut R, options: &InterpreterOptions) -> (Tape<C>, InterpretationResult)
+    where C: Cell,
+          R: ByteSource,
+          W: ByteWriter, {
+    interpret_with_hook::<C, R, W, _>(node, out, src, options, &mut NoHook)
+}
 
-    let sleep = match sleep {
-        Some(time) => Some(Duration::from_millis(time as u64)),
-        None => None,
+/// Interprets a program like interpret_with_tape does, calling the hook before executing each node.
+pub fn interpret_with_hook<C, R, W, H>(node: &InstructionNode, out: &mut W, src: &mut R, options: &InterpreterOptions, hook: &mut H) -> (Tape<C>, InterpretationResult)
+    where C: Cell,
+          R: ByteSource,
+          W: ByteWriter,
+          H: InterpreterHook<C>, {
+    let mut context = Context::<C>::new(options);
+
+    let result = context.interpret_node(node, out, src, hook);
+    let tape = Tape {
+        memory: context.memory,
+        pointer: context.p,
+        origin: context.origin,
+        steps: context.steps,
     };
+    (tape, result)
+}
+
+/// Interprets a program like interpret does,
+/// while counting how often each kind of instruction is executed.
+pub fn profile<C, R, W>(node: &InstructionNode, out: &mut W, src: &mut R, options: &InterpreterOptions) -> (Profile, InterpretationResult)
+    where C: Cell,
+          R: ByteSource,
+          W: ByteWriter, {
+    let mut context = Context::<C>::new(options);
+    context.profile = Some(Profile::default());
+
+    let result = context.interpret_node(node, out, src, &mut NoHook);
+    (context.profile.unwrap(), result)
+}
+
+
+/// An interpreter that can be paused after any instruction and resumed later,
+/// for debuggers and other tools that need to look at the program while it runs.
+/// Like interpret, it keeps a stack of the node lists it is in the middle of instead of recursing into loops,
+/// but checking whether to pause between every node makes it slower, though otherwise it behaves just the same.
+pub struct Machine<'a, C: Cell> {
+    context: Context<C>,
+    stack: Vec<Frame<'a>>,
+}
+/// A list of nodes being executed, either the children of a Program or the body of a loop.
+struct Frame<'a> {
+    nodes: &'a [InstructionNode],
+    /// The index of the next node to execute.
+    index: usize,
+    /// The loop this is the body of, which is tested again once the end is reached.
+    loop_node: Option<&'a InstructionNode>,
+}
+impl<'a, C: Cell> Machine<'a, C> {
+    pub fn new(program: &'a InstructionNode, options: &InterpreterOptions) -> Machine<'a, C> {
+        Machine {
+            context: Context::new(options),
+            stack: vec![Frame {
+                nodes: std::slice::from_ref(program),
+                index: 0,
+                loop_node: None,
+            }],
+        }
+    }
+
+    /// The tape and pointer as they are between instructions.
+    pub fn context(&self) -> &Context<C> {
+        &self.context
+    }
+    pub fn is_finished(&self) -> bool {
+        self.stack.is_empty()
+    }
+    pub fn into_tape(self) -> Tape<C> {
+        Tape {
+            memory: self.context.memory,
+            pointer: self.context.p,
+            origin: self.context.origin,
+            steps: self.context.steps,
+        }
+    }
+
+    /// Executes the next node, which for a Program or Loop only means entering or skipping it.
+    /// Does nothing once the program has finished.
+    /// After an error, the machine is left where it failed and shouldn't be stepped any further.
+    pub fn step<R, W>(&mut self, out: &mut W, src: &mut R) -> InterpretationResult
+        where R: ByteSource,
+              W: ByteWriter,
+    {
+        self.step_with_hook(out, src, &mut NoHook)
+    }
+    pub fn step_with_hook<R, W, H>(&mut self, out: &mut W, src: &mut R, hook: &mut H) -> InterpretationResult
+        where R: ByteSource,
+              W: ByteWriter,
+              H: InterpreterHook<C>,
+    {
+        let node = match self.next_node()? {
+            Some(node) => node,
+            None => return Ok(()),
+        };
+        self.stack.last_mut().unwrap().index += 1;
+
+        hook.before_node(node, &self.context);
+
+        match &node.node_type {
+            NodeType::Program(nodes) => self.stack.push(Frame {
+                nodes,
+                index: 0,
+                loop_node: None,
+            }),
+            NodeType::Loop(nodes) => {
+                if self.context.test_loop(node)? {
+                    self.stack.push(Frame {
+                        nodes,
+                        index: 0,
+                        loop_node: Some(node),
+                    });
+                }
+                else {
+                    self.context.sleep();
+                }
+            }
+            _ => {
+                self.context.execute_instruction(node, out, src)?;
+                self.context.sleep();
+            }
+        }
+
+        Ok(())
+    }
+    /// Runs the program until it finishes.
+    pub fn run<R, W>(&mut self, out: &mut W, src: &mut R) -> InterpretationResult
+        where R: ByteSource,
+              W: ByteWriter,
+    {
+        self.run_with_hook(out, src, &mut NoHook)
+    }
+    pub fn run_with_hook<R, W, H>(&mut self, out: &mut W, src: &mut R, hook: &mut H) -> InterpretationResult
+        where R: ByteSource,
+              W: ByteWriter,
+              H: InterpreterHook<C>,
+    {
+        while !self.is_finished() {
+            self.step_with_hook(out, src, hook)?;
+        }
+
+        Ok(())
+    }
+    /// Runs the program until it finishes, or stops right before a node the predicate returns true for.
+    /// Calling it again with the same predicate stops at the same node, so step past it first.
+    pub fn run_until<R, W, P>(&mut self, out: &mut W, src: &mut R, mut predicate: P) -> InterpretationResult
+        where R: ByteSource,
+              W: ByteWriter,
+              P: FnMut(&InstructionNode, &Context<C>) -> bool,
+    {
+        while let Some(node) = self.next_node()? {
+            if predicate(node, &self.context) {
+                break;
+            }
+            self.step(out, src)?;
+        }
+
+        Ok(())
+    }
+
+    /// Finishes any node lists that have run out, testing loops again at the end of their body,
+    /// and returns the node the next step is going to execute.
+    fn next_node(&mut self) -> Result<Option<&'a InstructionNode>, InterpretationError> {
+        loop {
+            let (nodes, index, loop_node) = match self.stack.last() {
+                Some(frame) => (frame.nodes, frame.index, frame.loop_node),
+                None => return Ok(None),
+            };
 
-    context.interpret_node(node, out, src, sleep)
+            if let Some(node) = nodes.get(index) {
+                return Ok(Some(node));
+            }
+
+            if let Some(loop_node) = loop_node {
+                if self.context.test_loop(loop_node)? {
+                    self.stack.last_mut().unwrap().index = 0;
+                    continue;
+                }
+            }
+
+            self.stack.pop();
+            self.context.sleep();
+        }
+    }
+}
+
+
+/// Gets called by interpret_with_hook before each node is executed,
+/// with the state of the interpreter right before it.
+/// Meant for building debuggers, tracers and the like on top of the interpreter.
+pub trait InterpreterHook<C: Cell> {
+    fn before_node(&mut self, node: &InstructionNode, context: &Context<C>);
+}
+impl<C: Cell, F: FnMut(&InstructionNode, &Context<C>)> InterpreterHook<C> for F {
+    fn before_node(&mut self, node: &InstructionNode, context: &Context<C>) {
+        self(node, context)
+    }
+}
+/// The hook used when there is none, doing nothing.
+pub struct NoHook;
+impl<C: Cell> InterpreterHook<C> for NoHook {
+    fn before_node(&mut self, _node: &InstructionNode, _context: &Context<C>) {}
+}
+
+
+/// A snapshot of the memory of the interpreter.
+#[derive(Clone, Debug)]
+pub struct Tape<C: Cell> {
+    pub memory: Memory<C>,
+    pub pointer: usize,
+    /// The index in memory of the cell the program started on.
+    /// Only ever nonzero with a bidirectional tape, once it has grown to the left.
+    pub origin: usize,
+    /// The number of nodes executed to get here.
+    pub steps: u64,
+}
+impl<C: Cell> Tape<C> {
+    pub fn cell(&self, index: usize) -> C {
+        self.memory.cell(index)
+    }
+    /// The cell the pointer is on.
+    pub fn current_cell(&self) -> C {
+        self.cell(self.pointer)
+    }
 }
 
 
 #[derive(Copy, Clone, Debug)]
 pub enum InterpretationError {
-    PointerUnderflow,
+    PointerUnderflow { line: u32, char: u32 },
+    PointerOverflow { line: u32, char: u32 },
+    PointerOutOfBounds { line: u32, char: u32 },
+    StepLimitExceeded { steps: u64 },
+    CellOverflow { line: u32, char: u32 },
 }
 impl Display for InterpretationError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::PointerUnderflow => write!(f, "Cell pointer underflow on pointer decrement")?,
+            Self::PointerUnderflow { line, char } => {
+                write!(f, "Cell pointer underflow on pointer decrement on line {}, char {}", line, char)?
+            }
+            Self::PointerOverflow { line, char } => {
+                write!(f, "Cell pointer overflow on pointer increment on line {}, char {}", line, char)?
+            }
+            Self::PointerOutOfBounds { line, char } => {
+                write!(f, "Cell pointer out of bounds of the fixed size tape on line {}, char {}", line, char)?
+            }
+            Self::StepLimitExceeded { steps } => {
+                write!(f, "Step limit of {} instructions exceeded", steps)?
+            }
+            Self::CellOverflow { line, char } => {
+                write!(f, "Cell value wrapped around on line {}, char {}", line, char)?
+            }
         }
         
         Ok(())
@@ -75,90 +608,482 @@ impl Display for InterpretationError {
 }
 pub type InterpretationResult = Result<(), InterpretationError>;
 
+fn underflow(node: &InstructionNode) -> InterpretationError {
+    InterpretationError::PointerUnderflow {
+        line: node.line,
+        char: node.char,
+    }
+}
+fn overflow(node: &InstructionNode) -> InterpretationError {
+    InterpretationError::PointerOverflow {
+        line: node.line,
+        char: node.char,
+    }
+}
+fn out_of_bounds(node: &InstructionNode) -> InterpretationError {
+    InterpretationError::PointerOutOfBounds {
+        line: node.line,
+        char: node.char,
+    }
+}
+fn cell_overflow(node: &InstructionNode) -> InterpretationError {
+    InterpretationError::CellOverflow {
+        line: node.line,
+        char: node.char,
+    }
+}
+
+/// The index the offset leads to on a circular tape of the length.
+fn wrap_index(index: usize, offset: isize, len: usize) -> usize {
+    (index as i128 + offset as i128).rem_euclid(len as i128) as usize
+}
 
 
-struct Context {
-    memory: Vec<u8>,
+
+/// How many cells on either side of the pointer the debug command prints.
+pub(crate) const DEBUG_RADIUS: usize = 8;
+
+/// The state of the interpreter while it runs, which hooks get to look at.
+pub struct Context<C: Cell> {
+    memory: Memory<C>,
     p: usize,
+    /// The index in memory of the starting cell, which moves right whenever a bidirectional tape grows left.
+    origin: usize,
+    steps: u64,
+    options: InterpreterOptions,
+    profile: Option<Profile>,
 }
-impl Context {
-    fn expand_memory(&mut self) {
-        while self.memory.len() <= self.p {
-            self.memory.push(0);
+impl<C: Cell> Context<C> {
+    fn new(options: &InterpreterOptions) -> Context<C> {
+        Context {
+            memory: if options.sparse_tape {
+                Memory::sparse()
+            }
+            else {
+                Memory::dense(options.tape_size)
+            }.with_fill(C::from_u64(options.init_cell as u64 * 0x0101_0101_0101_0101)),
+            p: 0,
+            origin: 0,
+            steps: 0,
+            options: *options,
+            profile: None,
+        }
+    }
+
+    /// All cells the program has touched so far.
+    pub fn memory(&self) -> &Memory<C> {
+        &self.memory
+    }
+    /// The index in memory of the current cell.
+    pub fn pointer(&self) -> usize {
+        self.p
+    }
+    /// The index in memory of the cell the program started on.
+    pub fn origin(&self) -> usize {
+        self.origin
+    }
+    /// The number of instructions executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    fn expand_memory(&mut self, node: &InstructionNode) -> InterpretationResult {
+        self.expand_memory_to(self.p, node)
+    }
+    fn expand_memory_to(&mut self, index: usize, node: &InstructionNode) -> InterpretationResult {
+        self.check_bounds(index, node)?;
+
+        self.memory.expand_to(index);
+
+        Ok(())
+    }
+    fn check_bounds(&self, index: usize, node: &InstructionNode) -> InterpretationResult {
+        match self.options.tape_bounds {
+            TapeBounds::Fixed(size) if index >= self.origin + size => Err(out_of_bounds(node)),
+            _ => Ok(()),
+        }
+    }
+    /// Makes room for the given number of cells in front of the pointer on a bidirectional tape,
+    /// or fails with a pointer underflow if the tape can't grow left.
+    fn ensure_left(&mut self, amount: usize, node: &InstructionNode) -> InterpretationResult {
+        if amount <= self.p {
+            return Ok(());
+        }
+        if !self.options.bidirectional {
+            return Err(underflow(node));
+        }
+
+        // Grow by at least the current size, so walking left only copies the tape a logarithmic number of times.
+        let missing = amount - self.p;
+        let grow_by = missing.max(self.memory.len());
+        self.memory.prepend(grow_by);
+        self.p += grow_by;
+        self.origin += grow_by;
+
+        Ok(())
+    }
+    /// Adds the amount to the cell at the index, which has to be in memory already.
+    /// Wraps around at the cell width, unless strict cells are on and it fails instead.
+    fn add_to_cell(&mut self, index: usize, amount: i128, node: &InstructionNode) -> InterpretationResult {
+        let cell = self.memory.cell_mut(index);
+        if self.options.strict_cells {
+            *cell = cell.checked_add_amount(amount).ok_or(cell_overflow(node))?;
+        }
+        else {
+            *cell = cell.wrapping_add(C::from_u64(amount as u64));
+        }
+
+        Ok(())
+    }
+    /// The length of the tape if the pointer wraps around it.
+    fn wrap_len(&self) -> Option<usize> {
+        match (self.options.pointer_mode, self.options.tape_bounds) {
+            (PointerMode::Wrap, TapeBounds::Fixed(size)) if size > 0 => Some(size),
+            _ => None,
+        }
+    }
+    fn offset_index(&mut self, offset: isize, node: &InstructionNode) -> Result<usize, InterpretationError> {
+        if let Some(len) = self.wrap_len() {
+            Ok(wrap_index(self.p, offset, len))
+        }
+        else if offset < 0 {
+            self.ensure_left(offset.wrapping_neg() as usize, node)?;
+            Ok(self.p - offset.wrapping_neg() as usize)
+        }
+        else {
+            self.p.checked_add(offset as usize).ok_or(overflow(node))
+        }
+    }
+    /// Describes the pointer and the cells within the radius of it, with the current cell in brackets.
+    /// Positions are relative to the starting cell, so they stay the same when a bidirectional tape grows left.
+    pub fn describe_tape(&self, radius: usize) -> String {
+        let pointer = self.p as isize - self.origin as isize;
+        let first = pointer.saturating_sub(radius as isize).max(-(self.origin as isize));
+        let last = pointer.saturating_add(radius as isize);
+
+        let mut cells = String::new();
+        for i in first..=last {
+            let index = (i + self.origin as isize) as usize;
+            let val = self.memory.cell(index).to_u64();
+            if i == pointer {
+                cells.push_str(&format!(" [{}]", val));
+            }
+            else {
+                cells.push_str(&format!(" {}", val));
+            }
+        }
+
+        format!("pointer {}, cells from {}:{}", pointer, first, cells)
+    }
+    /// Prints the tape within DEBUG_RADIUS of the pointer to stderr.
+    fn print_debug(&self, node: &InstructionNode) {
+        eprintln!("\nDebug on line {}, char {}: {}", node.line, node.char, self.describe_tape(DEBUG_RADIUS));
+    }
+    /// Moves the pointer by the stride until it points to a zero cell, wrapping around the tape.
+    fn scan_wrapping(&mut self, stride: isize) -> InterpretationResult {
+        let len = self.wrap_len().unwrap();
+
+        // Within len strides, the pointer has been to every cell it is ever going to reach.
+        for _ in 0..len {
+            if self.memory.cell(self.p).is_zero() {
+                return Ok(());
+            }
+            self.p = wrap_index(self.p, stride, len);
+        }
+
+        // Without a zero cell to stop at, the loop this was collapsed from never ends either.
+        loop {
+            self.step()?;
         }
     }
+    /// Moves the pointer by the stride until it points to a zero cell, one cell at a time.
+    /// Used when the cells past the end of memory aren't zero, so the scan grows the tape as it goes,
+    /// and only ends by running out of steps or tape, like the loop it was collapsed from.
+    fn scan_past_memory(&mut self, stride: isize, node: &InstructionNode) -> InterpretationResult {
+        while !self.memory.cell(self.p).is_zero() {
+            self.step()?;
+            if stride > 0 {
+                self.p = self.p.checked_add(stride as usize).ok_or(overflow(node))?;
+                self.expand_memory(node)?;
+            }
+            else {
+                self.ensure_left(stride.wrapping_neg() as usize, node)?;
+                self.p -= stride.wrapping_neg() as usize;
+            }
+        }
+
+        Ok(())
+    }
+    fn step(&mut self) -> InterpretationResult {
+        if let Some(max) = self.options.max_steps {
+            if self.steps >= max {
+                return Err(InterpretationError::StepLimitExceeded { steps: max });
+            }
+        }
+        self.steps += 1;
+
+        Ok(())
+    }
 
 
-    fn interpret_node<W, R>(&mut self, node: &InstructionNode, out: &mut W, src: &mut R, sleep: Option<Duration>) -> InterpretationResult
+    /// Runs the node to completion.
+    /// Keeps a stack of the node lists it is in the middle of instead of recursing into loops,
+    /// so arbitrarily deeply nested programs can't overflow the native stack.
+    fn interpret_node<W, R, H>(&mut self, node: &InstructionNode, out: &mut W, src: &mut R, hook: &mut H) -> InterpretationResult
         where R: ByteSource,
               W: ByteWriter,
+              H: InterpreterHook<C>,
     {
-        match &node.node_type {
-            NodeType::Program(nodes) => {
-                for child in nodes {
-                    self.interpret_node(child, out, src, sleep)?;
+        let mut stack = vec![Frame {
+            nodes: std::slice::from_ref(node),
+            index: 0,
+            loop_node: None,
+        }];
+
+        while let Some(frame) = stack.last_mut() {
+            let nodes = frame.nodes;
+            let node = match nodes.get(frame.index) {
+                Some(node) => node,
+                None => {
+                    if let Some(loop_node) = frame.loop_node {
+                        if self.test_loop(loop_node)? {
+                            frame.index = 0;
+                            continue;
+                        }
+                    }
+
+                    // Finishing the outermost list isn't the end of any node, so there's nothing to sleep after.
+                    stack.pop();
+                    if !stack.is_empty() {
+                        self.sleep();
+                    }
+                    continue;
                 }
+            };
+            frame.index += 1;
+
+            hook.before_node(node, self);
+
+            if let Some(profile) = &mut self.profile {
+                profile.record(&node.node_type);
             }
-            NodeType::Loop(nodes) => {
-                loop {
-                    self.expand_memory();
-                    if self.memory[self.p] == 0 {
-                        break;
-                    } else {
-                        for child in nodes {
-                            self.interpret_node(child, out, src, sleep)?;
-                        }
+
+            match &node.node_type {
+                NodeType::Program(nodes) => stack.push(Frame {
+                    nodes,
+                    index: 0,
+                    loop_node: None,
+                }),
+                NodeType::Loop(nodes) => {
+                    if self.test_loop(node)? {
+                        stack.push(Frame {
+                            nodes,
+                            index: 0,
+                            loop_node: Some(node),
+                        });
+                    }
+                    else {
+                        self.sleep();
                     }
                 }
+                _ => {
+                    self.execute_instruction(node, out, src)?;
+                    self.sleep();
+                }
             }
+        }
+
+        Ok(())
+    }
+    /// Counts a test of the loop condition as a step,
+    /// and returns whether the loop body is going to be executed.
+    fn test_loop(&mut self, node: &InstructionNode) -> Result<bool, InterpretationError> {
+        self.step()?;
+        self.expand_memory(node)?;
+        if self.memory.cell(self.p).is_zero() {
+            Ok(false)
+        }
+        else {
+            if let Some(profile) = &mut self.profile {
+                profile.loop_iterations += 1;
+            }
+            Ok(true)
+        }
+    }
+    fn sleep(&self) {
+        if let Some(time) = self.options.sleep {
+            std::thread::sleep(Duration::from_millis(time as u64));
+        }
+    }
+
+    /// Executes any node that isn't a Program or Loop, which interpret_node and the Machine walk themselves.
+    fn execute_instruction<W, R>(&mut self, node: &InstructionNode, out: &mut W, src: &mut R) -> InterpretationResult
+        where R: ByteSource,
+              W: ByteWriter,
+    {
+        match &node.node_type {
+            NodeType::Program(_) | NodeType::Loop(_) => unreachable!("blocks are walked by the caller"),
             NodeType::Next(amount) => {
-                self.p += amount;
+                self.step()?;
+                if let Some(len) = self.wrap_len() {
+                    self.p = wrap_index(self.p, *amount as isize, len);
+                }
+                else {
+                    self.p = self.p.checked_add(*amount).ok_or(overflow(node))?;
+                }
             }
             NodeType::Previous(amount) => {
-                if self.p == 0 && *amount != 0 {
-                    return Err(InterpretationError::PointerUnderflow);
+                self.step()?;
+                if let Some(len) = self.wrap_len() {
+                    self.p = wrap_index(self.p, (*amount as isize).wrapping_neg(), len);
+                }
+                else {
+                    self.ensure_left(*amount, node)?;
+                    self.p -= *amount;
                 }
-
-                self.p -= amount;
             }
             NodeType::Increment(amount) => {
-                self.expand_memory();
+                self.step()?;
+                self.expand_memory(node)?;
 
-                let cell = &mut self.memory[self.p];
-                *cell = cell.wrapping_add((*amount % 256) as u8);
+                self.add_to_cell(self.p, *amount as i128, node)?;
             }
             NodeType::Decrement(amount) => {
-                self.expand_memory();
+                self.step()?;
+                self.expand_memory(node)?;
 
-                let cell = &mut self.memory[self.p];
-                *cell = cell.wrapping_sub((*amount % 256) as u8);
+                self.add_to_cell(self.p, -(*amount as i128), node)?;
             }
             NodeType::Output => {
-                self.expand_memory();
+                self.step()?;
+                self.expand_memory(node)?;
 
-                let val = self.memory[self.p];
-                out.write(val);
+                let val = self.memory.cell(self.p);
+                out.write(val.to_byte());
             }
             NodeType::Input => {
-                self.expand_memory();
+                self.step()?;
+                self.expand_memory(node)?;
 
-                if let Some(val) = src.read() {
-                    self.memory[self.p] = val;
+                out.flush();
+                match src.read() {
+                    Some(val) => *self.memory.cell_mut(self.p) = C::from_byte(val),
+                    None => match self.options.eof_policy {
+                        EofPolicy::Unchanged => (),
+                        EofPolicy::Zero => *self.memory.cell_mut(self.p) = C::default(),
+                        EofPolicy::NegativeOne => *self.memory.cell_mut(self.p) = C::from_u64(u64::MAX),
+                    },
                 }
             }
 
+            NodeType::Debug => {
+                out.flush();
+                self.print_debug(node);
+            }
+
             NodeType::SetCell(val) => {
-                self.expand_memory();
+                self.step()?;
+                self.expand_memory(node)?;
 
-                self.memory[self.p] = (*val % 256) as u8;
+                *self.memory.cell_mut(self.p) = C::from_u64(*val as u64);
             }
-        }
+            NodeType::MultiplyAdd { targets } => {
+                self.step()?;
+                self.expand_memory(node)?;
+
+                let val = self.memory.cell(self.p);
+                if !val.is_zero() {
+                    for &(offset, factor) in targets {
+                        let target = self.offset_index(offset, node)?;
+                        self.expand_memory_to(target, node)?;
+
+                        self.add_to_cell(target, val.to_u64() as i128 * factor as i128, node)?;
+                    }
+                }
+            }
+            NodeType::MoveAdd { offset } => {
+                self.step()?;
+                self.expand_memory(node)?;
+
+                let val = self.memory.cell(self.p);
+                if !val.is_zero() {
+                    let target = self.offset_index(*offset, node)?;
+                    self.expand_memory_to(target, node)?;
+
+                    self.add_to_cell(target, val.to_u64() as i128, node)?;
+                }
+            }
+            NodeType::ScanRight(stride) if self.wrap_len().is_some() => {
+                self.step()?;
+                self.scan_wrapping(*stride as isize)?;
+            }
+            NodeType::ScanLeft(stride) if self.wrap_len().is_some() => {
+                self.step()?;
+                self.scan_wrapping((*stride as isize).wrapping_neg())?;
+            }
+            NodeType::ScanRight(stride) => {
+                self.step()?;
+                self.expand_memory(node)?;
+
+                match self.memory.scan_right(self.p, *stride) {
+                    Some(steps) => {
+                        self.p += steps * stride;
+                        self.check_bounds(self.p, node)?;
+                    }
+                    None => self.scan_past_memory(*stride as isize, node)?,
+                }
+            }
+            NodeType::ScanLeft(stride) => {
+                self.step()?;
+                self.expand_memory(node)?;
 
+                let found = self.memory.scan_left(self.p, *stride);
+                let steps = match found {
+                    Some(steps) => steps,
+                    // Cells left of the tape are zero once it grows there, so stop at the first one of those.
+                    None if self.memory.fill().is_zero() => self.p / stride + 1,
+                    None => return self.scan_past_memory((*stride as isize).wrapping_neg(), node),
+                };
+                self.ensure_left(steps * stride, node)?;
+                self.p -= steps * stride;
+            }
+            NodeType::AddAtOffset { offset, amount } => {
+                self.step()?;
+                let target = self.offset_index(*offset, node)?;
+                self.expand_memory_to(target, node)?;
 
-        if let Some(time) = sleep {
-            std::thread::sleep(time);
+                self.add_to_cell(target, *amount as i128, node)?;
+            }
+            NodeType::SetAtOffset { offset, value } => {
+                self.step()?;
+                let target = self.offset_index(*offset, node)?;
+                self.expand_memory_to(target, node)?;
+
+                *self.memory.cell_mut(target) = C::from_u64(*value as u64);
+            }
         }
 
         Ok(())
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::front_end::lexer::lex;
+    use crate::front_end::parser::{parse_with_options, ParserOptions};
+
+    #[test]
+    fn deeply_nested_loops_run() {
+        const DEPTH: usize = 100_000;
+
+        let src = format!("+{}-{}{}.", "[".repeat(DEPTH), "]".repeat(DEPTH), "+".repeat(65));
+        let options = ParserOptions { max_loop_depth: DEPTH };
+        let program = parse_with_options(&lex(&src), &options).unwrap();
+
+        let mut out = VecWriter(Vec::new());
+        interpret::<u8, _, _>(&program, &mut out, &mut SliceSource::new(Vec::new()), &InterpreterOptions::default()).unwrap();
+        assert_eq!(out.0, b"A");
+    }
+}
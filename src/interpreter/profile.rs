@@ -0,0 +1,61 @@
+use crate::front_end::parser::NodeType;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+
+
+
+/// How often each kind of instruction was executed during a profiled run.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    /// Executions per instruction kind, keyed by the name of the NodeType variant.
+    pub counts: HashMap<&'static str, u64>,
+    /// The total number of loop body iterations across all loops.
+    pub loop_iterations: u64,
+}
+impl Profile {
+    pub(crate) fn record(&mut self, node_type: &NodeType) {
+        *self.counts.entry(node_name(node_type)).or_insert(0) += 1;
+    }
+
+    /// The instruction counts, from most to least executed.
+    pub fn sorted_counts(&self) -> Vec<(&'static str, u64)> {
+        let mut counts: Vec<_> = self.counts.iter()
+            .map(|(&name, &count)| (name, count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts
+    }
+}
+impl Display for Profile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (name, count) in self.sorted_counts() {
+            writeln!(f, "{:>12} {}", name, count)?;
+        }
+        writeln!(f, "{:>12} {}", "Iterations", self.loop_iterations)?;
+
+        Ok(())
+    }
+}
+
+
+fn node_name(node_type: &NodeType) -> &'static str {
+    match node_type {
+        NodeType::Program(_) => "Program",
+        NodeType::Next(_) => "Next",
+        NodeType::Previous(_) => "Previous",
+        NodeType::Increment(_) => "Increment",
+        NodeType::Decrement(_) => "Decrement",
+        NodeType::Output => "Output",
+        NodeType::Input => "Input",
+        NodeType::Debug => "Debug",
+        NodeType::Loop(_) => "Loop",
+        NodeType::SetCell(_) => "SetCell",
+        NodeType::MultiplyAdd { .. } => "MultiplyAdd",
+        NodeType::MoveAdd { .. } => "MoveAdd",
+        NodeType::ScanRight(_) => "ScanRight",
+        NodeType::ScanLeft(_) => "ScanLeft",
+        NodeType::AddAtOffset { .. } => "AddAtOffset",
+        NodeType::SetAtOffset { .. } => "SetAtOffset",
+    }
+}
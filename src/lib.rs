@@ -0,0 +1,24 @@
+//! A brainfuck compiler and interpreter.
+//!
+//! Programs go through lex, parse and optionally apply_default_optimizations,
+//! after which the resulting tree can either be run with interpret
+//! or turned into LLVM bitcode with compile_to_ir.
+//! run_source does all of that in one call, for running a program on in-memory input,
+//! and run_to_string does the same with text, for comparing a program's output to what it should be.
+
+pub mod front_end;
+pub mod interpreter;
+pub mod optimizer;
+pub mod compiler;
+mod analysis;
+mod run;
+mod timing;
+
+pub use front_end::lexer::lex;
+pub use front_end::parser::parse;
+pub use optimizer::apply_default_optimizations;
+pub use interpreter::interpret;
+pub use compiler::compile_to_ir;
+pub use run::{run_source, run_to_string, verify_compiled, RunError, Verification};
+pub use analysis::{find_warnings, Warning};
+pub use timing::PhaseTimes;
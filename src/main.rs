@@ -1,181 +1,499 @@
 #![allow(dead_code, unused_imports)]
 
-use crate::front_end::lexer::{lex};
-use crate::front_end::parser::{parse, print_tree, InstructionNode};
+use rustfuck::front_end::lexer::{lex_with_options, print_tokens, split_input, LexerOptions};
+use rustfuck::front_end::parser::{parse_with_options, print_tree, to_dot, metrics, InstructionNode, ParserOptions};
+#[cfg(feature = "serde")]
+use rustfuck::front_end::to_json;
 use std::io::stdout;
-use crate::optimizer::apply_default_optimizations;
-use crate::interpreter::{interpret, StdOutWriter, StdInSource};
-use crate::compiler::compile_to_ir;
+use rustfuck::optimizer::{default_optimizer, find_pass, Optimizer, PASSES, OPTIONAL_PASSES, ZERO_TAPE_PASSES, LINEAR_TAPE_PASSES, WRAPPING_PASSES, DEFAULT_MAX_ITERATIONS};
+use rustfuck::interpreter::{interpret_with_tape, interpret_with_hook, profile, StdOutWriter, BufferedStdOutWriter, FileWriter, ByteWriter, StdInSource, BufferedStdInSource, FileSource, NormalizeNewlines, ByteSource, SliceSource, VecWriter, EofPolicy, InterpreterOptions, InterpretationResult, InterpretationError, TapeBounds, PointerMode};
+use rustfuck::interpreter::{Cell, Debugger};
+use rustfuck::{find_warnings, verify_compiled, PhaseTimes};
+use rustfuck::compiler::{compile_to_c, compile_to_ir_text, compile_to_cfg_text, compile_to_executable, compile_to_executable_timed, jit_run, CellWidth, CodegenOptions, CompileOptions, CompileError, TargetOs, EntryPoint};
 use clap::Clap;
-use std::path::{PathBuf, Path};
-use std::process::Command;
-
-mod front_end;
-mod interpreter;
-mod optimizer;
-mod compiler;
+use std::path::{Path, PathBuf};
+use std::io::Read;
+use std::ffi::OsString;
+use std::time::{Duration, Instant};
 
 fn main() {
     let opts: Opts = Opts::parse();
-    let source = match std::fs::read_to_string(&opts.input_path) {
+
+    if opts.list_passes {
+        for (name, _) in PASSES {
+            println!("{}", name);
+        }
+        for (name, _) in OPTIONAL_PASSES {
+            println!("{} (only when named in --passes)", name);
+        }
+        return;
+    }
+
+    if opts.init_cell != 0 && opts.target_os == TargetOs::Wasm32 {
+        eprintln!("--init-cell is not supported for wasm32, whose fresh memory is always zero");
+        return;
+    }
+
+    let mut times = PhaseTimes::new();
+
+    let input_path = opts.input_path.as_deref().unwrap();
+    let mut source = match times.time("read", || read_source(input_path)) {
         Ok(src) => src,
         Err(err) => {
-            eprintln!("Failed to read input file {}: {}", opts.input_path, err);
+            eprintln!("{}", CompileError::ReadSource(err));
             return;
         }
     };
-    let tokens = lex(&source);
-    let mut node = match parse(&tokens) {
+    // The input following the program in the source file, with --split-input.
+    let mut embedded_input = None;
+    if opts.split_input {
+        let (program, input) = split_input(&source);
+        embedded_input = input.map(|input| input.as_bytes().to_vec());
+        source = program.to_string();
+    }
+    let tokens = times.time("lex", || lex_with_options(&source, &opts.lexer_options()));
+    times.count("tokens", tokens.len());
+
+    if opts.emit_tokens {
+        if let Err(err) = print_tokens(&tokens, &mut stdout()) {
+            eprintln!("Failed to print tokens: {}", err);
+        }
+        return;
+    }
+
+    let mut node = match times.time("parse", || parse_with_options(&tokens, &opts.parser_options())) {
         Ok(node) => node,
-        Err(err) => {
-            eprintln!("Failed to parse brainfuck program: {}", err);
+        Err(errors) => {
+            for err in errors {
+                eprintln!("Failed to parse brainfuck program: {}\n", err.render(&source));
+            }
             return;
         }
     };
+    times.count("nodes", node.node_count());
+    if opts.stats {
+        eprintln!("Program stats:\n{}", metrics(&node));
+    }
+
+    // A wrapping pointer can lead a loop back to the cell it checks, and the analysis expects a zeroed tape.
+    if !opts.no_warnings && !opts.wrap_pointer && opts.init_cell == 0 {
+        for warning in find_warnings(&node) {
+            eprintln!("Warning: {}", warning);
+        }
+    }
 
 
     if !opts.disable_opt {
-        apply_default_optimizations(&mut node);
+        let optimizer = match &opts.passes {
+            Some(passes) => match Optimizer::from_names(passes.split(',').map(str::trim)) {
+                Ok(optimizer) => optimizer,
+                Err(name) => {
+                    eprintln!("Unknown optimizer pass: {}. Use --list-passes to see all of them.", name);
+                    return;
+                }
+            },
+            // Input only overwrites the cell for certain when it changes the cell at the end of the input too.
+            None if opts.eof_policy != EofPolicy::Unchanged => {
+                default_optimizer().with_pass("remove_writes_before_input", find_pass("remove_writes_before_input").unwrap())
+            }
+            None => default_optimizer(),
+        };
+        let optimizer = if opts.init_cell != 0 { optimizer.without_passes(ZERO_TAPE_PASSES) } else { optimizer };
+        let optimizer = if opts.wrap_pointer { optimizer.without_passes(LINEAR_TAPE_PASSES) } else { optimizer };
+        let optimizer = if opts.strict_cells { optimizer.without_passes(WRAPPING_PASSES) } else { optimizer };
+        let optimizer = if opts.opt_stats { optimizer.with_stats() } else { optimizer };
+
+        let stats = times.time("optimize", || match &opts.passes {
+            Some(_) => optimizer.apply(&mut node),
+            None => optimizer.apply_fixpoint(&mut node, DEFAULT_MAX_ITERATIONS),
+        });
+        times.count("nodes after optimization", node.node_count());
+        if opts.opt_stats {
+            eprintln!("Optimizer stats:\n{}", stats);
+        }
+        if opts.stats {
+            eprintln!("Program stats after optimization:\n{}", metrics(&node));
+        }
+    }
+
+    if opts.emit_ast {
+        if let Err(err) = print_tree(&node, &mut stdout(), &String::new(), true) {
+            eprintln!("Failed to print syntax tree: {}", err);
+        }
+        return;
+    }
+
+    if opts.emit_dot {
+        if let Err(err) = to_dot(&node, &mut stdout()) {
+            eprintln!("Failed to print syntax tree graph: {}", err);
+        }
+        return;
+    }
+
+    #[cfg(feature = "serde")]
+    if opts.emit_ast_json {
+        println!("{}", to_json(&node));
+        return;
     }
 
 
+    if opts.profile {
+        let options = opts.interpreter_options();
+        let mut input = match input_source(&opts, embedded_input.as_deref()) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("Failed to open input file: {}", err);
+                return;
+            }
+        };
+        let mut output = match output_writer(&opts) {
+            Ok(output) => output,
+            Err(err) => {
+                eprintln!("Failed to create output file: {}", err);
+                return;
+            }
+        };
+        let (profile, result) = times.time("interpret", || match opts.cell_width {
+            CellWidth::Bits8 => profile::<u8, _, _>(&node, &mut output, &mut input, &options),
+            CellWidth::Bits16 => profile::<u16, _, _>(&node, &mut output, &mut input, &options),
+            CellWidth::Bits32 => profile::<u32, _, _>(&node, &mut output, &mut input, &options),
+        });
+        output.flush();
 
-    if opts.interpret {
-        let result = interpret(&node, &mut StdOutWriter, &mut StdInSource, opts.slow_down);
+        if let Err(err) = result {
+            eprintln!("\nEncountered error during execution: {}", err);
+        }
+        eprintln!("\nProfile:\n{}", profile);
+    }
+    else if let Some(runs) = opts.benchmark {
+        let result = times.time("benchmark", || match opts.cell_width {
+            CellWidth::Bits8 => run_benchmark::<u8>(&node, &opts, embedded_input.as_deref(), runs),
+            CellWidth::Bits16 => run_benchmark::<u16>(&node, &opts, embedded_input.as_deref(), runs),
+            CellWidth::Bits32 => run_benchmark::<u32>(&node, &opts, embedded_input.as_deref(), runs),
+        });
 
         if let Err(err) = result {
             eprintln!("\nEncountered error during execution: {}", err);
         }
     }
+    else if opts.verify {
+        let result = read_all_input(&opts, embedded_input.as_deref())
+            .map_err(|err| format!("Failed to read input: {}", err))
+            .and_then(|input| times.time("verify", || match opts.cell_width {
+                CellWidth::Bits8 => verify_compiled::<u8>(&node, &opts.compile_options(), &opts.interpreter_options(), input),
+                CellWidth::Bits16 => verify_compiled::<u16>(&node, &opts.compile_options(), &opts.interpreter_options(), input),
+                CellWidth::Bits32 => verify_compiled::<u32>(&node, &opts.compile_options(), &opts.interpreter_options(), input),
+            }));
+
+        match result {
+            Ok(verification) => {
+                if let Some(err) = &verification.interpreter_error {
+                    eprintln!("Interpreter stopped with an error, comparing the output up to it: {}", err);
+                }
+                eprintln!("{}", verification);
+                if !verification.outputs_match() {
+                    print_times(&times, &opts);
+                    std::process::exit(1);
+                }
+            }
+            Err(err) => eprintln!("{}\nFailed to verify program. Terminating...", err),
+        }
+    }
+    else if opts.interpret {
+        let input = match input_source(&opts, embedded_input.as_deref()) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("Failed to open input file: {}", err);
+                return;
+            }
+        };
+        let output = match output_writer(&opts) {
+            Ok(output) => output,
+            Err(err) => {
+                eprintln!("Failed to create output file: {}", err);
+                return;
+            }
+        };
+        let result = times.time("interpret", || match opts.cell_width {
+            CellWidth::Bits8 => run_interpreter::<u8>(&node, &opts, input, output),
+            CellWidth::Bits16 => run_interpreter::<u16>(&node, &opts, input, output),
+            CellWidth::Bits32 => run_interpreter::<u32>(&node, &opts, input, output),
+        });
+
+        match result {
+            Ok(code) if opts.exit_from_cell => {
+                print_times(&times, &opts);
+                std::process::exit(code);
+            }
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!("\nEncountered error during execution: {}", err);
+                if opts.exit_from_cell {
+                    print_times(&times, &opts);
+                    std::process::exit(INTERPRETER_ERROR_EXIT_CODE);
+                }
+            }
+        }
+    }
+    else if opts.emit_ir {
+        match times.time("codegen", || emit_ir(&node, &opts)) {
+            Ok(()) => (),
+            Err(err) => eprintln!("{}\nFailed to emit IR. Terminating...", err),
+        }
+    }
+    else if opts.emit_cfg {
+        match times.time("codegen", || emit_cfg(&node, &opts)) {
+            Ok(()) => (),
+            Err(err) => eprintln!("{}\nFailed to emit CFG. Terminating...", err),
+        }
+    }
+    else if opts.emit_c {
+        match times.time("codegen", || emit_c(&node, &opts)) {
+            Ok(()) => (),
+            Err(err) => eprintln!("{}\nFailed to emit C. Terminating...", err),
+        }
+    }
+    else if opts.run {
+        match times.time("jit", || jit_run(&node, "jit", &opts.codegen_options())) {
+            Ok(code) => {
+                print_times(&times, &opts);
+                std::process::exit(code);
+            }
+            Err(err) => {
+                eprintln!("Failed to run program with JIT: {}", err);
+                print_times(&times, &opts);
+                std::process::exit(1);
+            }
+        }
+    }
     else {
-        match compile(&node, &opts) {
+        match compile_to_executable_timed(&node, &opts.compile_options(), &mut times) {
             Ok(()) => (),
-            Err(()) => eprintln!("Compilation failed. Terminating..."),
+            Err(err) => eprintln!("{}\nCompilation failed. Terminating...", err),
         }
     }
+
+    print_times(&times, &opts);
 }
 
+fn print_times(times: &PhaseTimes, opts: &Opts) {
+    if opts.time {
+        eprintln!("\nTimes:\n{}", times);
+    }
+}
 
-fn compile(program: &InstructionNode, opts: &Opts) -> Result<(), ()> {
-    let in_path = PathBuf::from(&opts.input_path);
-    let mut out_path;
-    if let Some(path) = &opts.output_path {
-        out_path = PathBuf::from(path);
+
+/// What the interpreter exits with on an error when run with --exit-from-cell.
+/// Every exit code a portable process can have is also a value some cell can end on,
+/// so this one is reserved instead, like 125 is by other tools running commands and reporting their exit code.
+/// A program that finishes on a cell of 125 can't be told apart from one that failed.
+const INTERPRETER_ERROR_EXIT_CODE: i32 = 125;
+
+/// Interprets the program and returns the value of the current cell once it finishes, to exit with.
+fn run_interpreter<C: Cell>(program: &InstructionNode, opts: &Opts, mut input: Box<dyn ByteSource>, mut output: Box<dyn ByteWriter>) -> Result<i32, InterpretationError> {
+    let (tape, result) = if opts.debug {
+        interpret_with_hook::<C, _, _, _>(program, &mut output, &mut input, &opts.interpreter_options(), &mut Debugger::new())
     }
     else {
-        out_path = PathBuf::from("./");
+        interpret_with_tape::<C, _, _>(program, &mut output, &mut input, &opts.interpreter_options())
+    };
+    output.flush();
+
+    if let Some(cells) = opts.dump_memory {
+        let dump: Vec<String> = (0..cells)
+            .map(|i| format!("{}", tape.cell(tape.origin + i).to_u64()))
+            .collect();
+        eprintln!("\nPointer: {}", tape.pointer as isize - tape.origin as isize);
+        eprintln!("Memory: [{}]", dump.join(", "));
+    }
+    if opts.cell_histogram {
+        let histogram = tape.memory.histogram();
+        eprintln!("\nCell histogram over {} cells:", tape.memory.len());
+        for (value, count) in histogram {
+            eprintln!("{:>10}: {}", value, count);
+        }
     }
 
-    let out_stem = match out_path.file_stem() {
-        Some(stem) => stem.to_os_string(),
-        None => in_path.file_stem().unwrap().to_os_string(),
+    result.map(|_| tape.current_cell().to_u64() as i32)
+}
+
+/// Interprets the program the given number of times, each time with the same input read up front,
+/// and prints how long the runs took. The program's output is thrown away.
+fn run_benchmark<C: Cell>(program: &InstructionNode, opts: &Opts, embedded_input: Option<&[u8]>, runs: u32) -> InterpretationResult {
+    let runs = runs.max(1);
+    let input = match read_all_input(opts, embedded_input) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("Failed to read input: {}", err);
+            return Ok(());
+        }
     };
-    let int_path = PathBuf::from(&opts.int_dir);
-    let mut bc_path = int_path.clone();
-    bc_path.push(format!("int_{}.bc", out_stem.to_str().unwrap()));
-    let mut obj_path = int_path.clone();
-    obj_path.push(format!("int_{}.o", out_stem.to_str().unwrap()));
-    let mut flush_path = int_path;
-    flush_path.push(format!("provint_flush_stdout_helper.o"));
 
+    let options = opts.interpreter_options();
+    let mut durations = Vec::with_capacity(runs as usize);
+    let mut steps = 0;
+    for _ in 0..runs {
+        let mut source = SliceSource::new(input.clone());
+        let mut output = VecWriter(Vec::new());
 
-    out_path.push(out_stem.clone());
+        let start = Instant::now();
+        let (tape, result) = interpret_with_tape::<C, _, _>(program, &mut output, &mut source, &options);
+        durations.push(start.elapsed());
 
-    match std::fs::create_dir_all(bc_path.parent().unwrap()) {
-        Ok(()) => (),
-        Err(err) => {
-            eprintln!("Failed to create intermediate directory: {}", err);
-            return Err(());
-        }
+        result?;
+        steps = tape.steps;
     }
-    match std::fs::create_dir_all(out_path.parent().unwrap()) {
-        Ok(()) => (),
-        Err(err) => {
-            eprintln!("Failed to create out directory: {}", err);
-            return Err(());
-        }
+
+    durations.sort();
+    let mean = durations.iter().sum::<Duration>() / runs;
+    let median = durations[durations.len() / 2];
+    let steps_per_second = steps as f64 / mean.as_secs_f64();
+
+    eprintln!("Benchmark of {} runs:", runs);
+    eprintln!("  min     {:>10.3} ms", durations[0].as_secs_f64() * 1000.0);
+    eprintln!("  median  {:>10.3} ms", median.as_secs_f64() * 1000.0);
+    eprintln!("  mean    {:>10.3} ms", mean.as_secs_f64() * 1000.0);
+    eprintln!("  {} steps per run, {:.0} steps per second", steps, steps_per_second);
+    Ok(())
+}
+
+
+/// The input embedded in the source file if there is any, otherwise the input file or stdin,
+/// with its line endings normalized if asked for.
+fn input_source(opts: &Opts, embedded_input: Option<&[u8]>) -> std::io::Result<Box<dyn ByteSource>> {
+    let source = raw_input_source(opts, embedded_input)?;
+    if opts.normalize_input {
+        Ok(Box::new(NormalizeNewlines::new(source)))
     }
+    else {
+        Ok(source)
+    }
+}
+fn raw_input_source(opts: &Opts, embedded_input: Option<&[u8]>) -> std::io::Result<Box<dyn ByteSource>> {
+    if let Some(input) = embedded_input {
+        Ok(Box::new(SliceSource::new(input.to_vec())))
+    }
+    else if let Some(path) = &opts.input_file {
+        Ok(Box::new(FileSource::open(Path::new(path))?))
+    }
+    else if opts.buffer_input {
+        Ok(Box::new(BufferedStdInSource::new()))
+    }
+    else {
+        Ok(Box::new(StdInSource))
+    }
+}
 
+/// All of the input input_source would give, read up front.
+fn read_all_input(opts: &Opts, embedded_input: Option<&[u8]>) -> std::io::Result<Vec<u8>> {
+    let input = read_all_raw_input(opts, embedded_input)?;
+    if opts.normalize_input {
+        let mut source = NormalizeNewlines::new(SliceSource::new(input));
+        Ok(std::iter::from_fn(|| source.read()).collect())
+    }
+    else {
+        Ok(input)
+    }
+}
+fn read_all_raw_input(opts: &Opts, embedded_input: Option<&[u8]>) -> std::io::Result<Vec<u8>> {
+    if let Some(input) = embedded_input {
+        Ok(input.to_vec())
+    }
+    else if let Some(path) = &opts.input_file {
+        std::fs::read(path)
+    }
+    else {
+        let mut input = Vec::new();
+        std::io::stdin().read_to_end(&mut input)?;
+        Ok(input)
+    }
+}
 
+/// The output file if there is one, otherwise stdout.
+/// Output to stdout is buffered, unless the program is slowed down or stepped through to watch it run.
+fn output_writer(opts: &Opts) -> std::io::Result<Box<dyn ByteWriter>> {
+    if let Some(path) = &opts.output_file {
+        Ok(Box::new(FileWriter::create(Path::new(path))?))
+    }
+    else if opts.slow_down.is_some() || opts.debug {
+        Ok(Box::new(StdOutWriter))
+    }
+    else {
+        Ok(Box::new(BufferedStdOutWriter::new()))
+    }
+}
 
-    let bc_module = compile_to_ir(program, out_stem.to_str().unwrap());
 
-    match std::fs::write(&bc_path, bc_module.as_slice()) {
-        Ok(()) => (),
-        Err(err) => {
-            eprintln!("Failed to write bytecode file {}: {}", bc_path.to_str().unwrap(), err);
-            return Err(());
-        }
-    };
+/// The environment variable naming the llc to use when --llc isn't given.
+const LLC_VAR: &str = "RUSTFUCK_LLC";
 
+/// The input path that makes the program be read from stdin instead of a file.
+const STDIN_PATH: &str = "-";
 
-    invoke_llc(&bc_path, &obj_path, opts)?;
-    write_flush_helper(&flush_path)?;
-    invoke_ld(&obj_path, &flush_path, &out_path)?;
+fn read_source(path: &str) -> std::io::Result<String> {
+    if path == STDIN_PATH {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)?;
+        Ok(source)
+    }
+    else {
+        std::fs::read_to_string(path)
+    }
+}
 
-    Ok(())
+/// The stem output files are named after when no output path is given.
+/// Programs read from stdin have no file name, so they fall back to "a".
+fn input_stem(opts: &Opts) -> OsString {
+    match opts.input_path.as_deref() {
+        Some(STDIN_PATH) | None => OsString::from("a"),
+        Some(path) => PathBuf::from(path).file_stem().unwrap().to_os_string(),
+    }
 }
 
-fn invoke_llc(bc_path: &Path, obj_path: &Path, opts: &Opts) -> Result<(), ()> {
-    if opts.opt_level > 3 {
-        eprintln!("Invalid optimization level: {}", opts.opt_level);
-        return Err(())
+/// The name of the source file, which compiled modules are named after
+/// so debug information can refer to it.
+fn source_name(opts: &Opts) -> String {
+    match opts.input_path.as_deref() {
+        Some(STDIN_PATH) | None => String::from("stdin"),
+        Some(path) => PathBuf::from(path).file_name().unwrap().to_string_lossy().into_owned(),
     }
+}
 
-    match Command::new("llc")
-        .arg("-o").arg(obj_path)
-        .arg(format!("{}", bc_path.to_str().unwrap()))
-        .arg("-filetype=obj")
-        .arg(format!("-O{}", opts.opt_level))
-        .output() {
-        Ok(output) => {
-            if !output.status.success() {
-                eprintln!("Llc returned failure exit status:\n {} ", String::from_utf8_lossy(&output.stderr));
-                return Err(())
-            }
-        },
-        Err(err) => {
-            eprintln!("Failed to invoke llc: {}", err);
-            return Err(());
-        }
-    };
 
-    Ok(())
+fn emit_ir(program: &InstructionNode, opts: &Opts) -> Result<(), CompileError> {
+    let ir = compile_to_ir_text(program, &source_name(opts), &opts.codegen_options());
+
+    match &opts.output_path {
+        Some(path) => std::fs::write(path, ir).map_err(CompileError::WriteIr),
+        None => {
+            print!("{}", ir);
+            Ok(())
+        }
+    }
 }
 
+fn emit_cfg(program: &InstructionNode, opts: &Opts) -> Result<(), CompileError> {
+    let cfg = compile_to_cfg_text(program, &source_name(opts), &opts.codegen_options());
 
-const FLUSH_OBJ: &[u8] = include_bytes!("./helper/flush_stdout.o");
-fn write_flush_helper(path: &Path) -> Result<(), ()> {
-    match std::fs::write(path, FLUSH_OBJ) {
-        Ok(()) => Ok(()),
-        Err(err) => {
-            eprintln!("Failed to write helper obj file {}: {}", path.to_str().unwrap(), err);
-            Err(())
+    match &opts.output_path {
+        Some(path) => std::fs::write(path, cfg).map_err(CompileError::WriteCfg),
+        None => {
+            print!("{}", cfg);
+            Ok(())
         }
     }
 }
 
-fn invoke_ld(obj_path: &Path, flush_path: &Path, out_path: &Path) -> Result<(), ()> {
-    match Command::new("ld")
-        .arg("-o").arg(out_path)
-        .arg("-dynamic-linker").arg("/lib64/ld-linux-x86-64.so.2")
-        .arg(obj_path)
-        .arg(flush_path)
-        .arg("-lc")
-        .output() {
-        Ok(output) => {
-            if !output.status.success() {
-                eprintln!("Ld returned failure exit status:\n {}", String::from_utf8_lossy(&output.stderr));
-                return Err(());
-            }
-        }
-        Err(err) => {
-            eprintln!("Failed to invoke ld command: {}", err);
-            return Err(());
-        }
-    };
+fn emit_c(program: &InstructionNode, opts: &Opts) -> Result<(), CompileError> {
+    let source = compile_to_c(program, &opts.codegen_options());
 
-    Ok(())
+    match &opts.output_path {
+        Some(path) => std::fs::write(path, source).map_err(CompileError::WriteC),
+        None => {
+            print!("{}", source);
+            Ok(())
+        }
+    }
 }
 
 
@@ -189,31 +507,352 @@ fn invoke_ld(obj_path: &Path, flush_path: &Path, out_path: &Path) -> Result<(),
 #[clap(version = "1.3", author = "Meryll")]
 struct Opts {
     /// The path of the brainfuck file to compile/interpret.
-    input_path: String,
+    /// Use - to read the program from stdin.
+    #[clap(required_unless_present("list-passes"))]
+    input_path: Option<String>,
 
     /// Interpret program instead of compiling.
     #[clap(short, long)]
     interpret: bool,
 
-    /// The path of the executable file to write results to when compiling.
+    /// Interpret the program this many times with the same input, read beforehand,
+    /// and print the min, median and mean time of the runs instead of the program's output.
+    #[clap(long, requires("interpret"), conflicts_with_all(&["debug", "profile"]))]
+    benchmark: Option<u32>,
+
+    /// Interpret the program and run it compiled on the same input, and report where their outputs differ.
+    /// Exits with 1 if they do.
+    #[clap(long, conflicts_with_all(&["interpret", "run", "profile", "benchmark", "emit-ir", "emit-c", "emit-cfg", "emit-ast", "emit-dot", "emit-tokens"]))]
+    verify: bool,
+
+    /// Interpret program and print how often each kind of instruction was executed.
+    #[clap(long, conflicts_with_all(&["run", "emit-ir", "emit-c", "emit-cfg", "emit-ast", "emit-dot", "emit-tokens"]))]
+    profile: bool,
+
+    /// Compile program in memory and run it immediately using LLVM's JIT,
+    /// without writing any intermediate or executable files.
+    #[clap(short, long)]
+    run: bool,
+
+    /// The path of the executable file to write results to when compiling,
+    /// or a directory to put it in, named after the input file.
     #[clap(short)]
     output_path: Option<String>,
 
+    /// Print the generated LLVM IR instead of compiling,
+    /// or write it to the output path if one is given.
+    #[clap(long("emit-ir"))]
+    emit_ir: bool,
+
+    /// Print the program translated to C instead of compiling,
+    /// or write it to the output path if one is given.
+    #[clap(long("emit-c"), conflicts_with_all(&["interpret", "run", "emit-ir"]))]
+    emit_c: bool,
+
+    /// Print the basic blocks of each function in the generated LLVM IR and the blocks they branch to,
+    /// instead of compiling, or write them to the output path if one is given.
+    #[clap(long("emit-cfg"), conflicts_with_all(&["interpret", "run", "emit-ir", "emit-c"]))]
+    emit_cfg: bool,
+
+    /// Print the tokens of the program and exit without parsing it.
+    #[clap(long("emit-tokens"), conflicts_with_all(&["interpret", "run", "emit-ir", "emit-c", "emit-cfg", "emit-ast", "emit-dot"]))]
+    emit_tokens: bool,
+
+    /// Print the syntax tree of the program after optimization and exit.
+    #[clap(long("emit-ast"), conflicts_with_all(&["interpret", "run", "emit-ir", "emit-c", "emit-cfg"]))]
+    emit_ast: bool,
+
+    /// Print the syntax tree of the program after optimization as a Graphviz digraph and exit.
+    #[clap(long("emit-dot"), conflicts_with_all(&["interpret", "run", "emit-ir", "emit-c", "emit-cfg", "emit-ast"]))]
+    emit_dot: bool,
+
+    /// Print the syntax tree of the program after optimization as JSON and exit.
+    #[cfg(feature = "serde")]
+    #[clap(long("emit-ast-json"), conflicts_with_all(&["interpret", "run", "emit-ir", "emit-c", "emit-cfg", "emit-ast", "emit-dot"]))]
+    emit_ast_json: bool,
+
+    /// The distance between tab stops when counting the char positions in error messages.
+    /// Use 1 to count tabs as a single char.
+    #[clap(long("tab-width"), default_value = "8")]
+    tab_width: u32,
+
+    /// How many loops deep the program may nest before it is rejected,
+    /// which keeps adversarially nested input from overflowing the stack.
+    #[clap(long("max-loop-depth"), default_value = "1000")]
+    max_loop_depth: usize,
+
+    /// Treat # as a command printing the pointer and the cells around it,
+    /// instead of as part of a comment.
+    #[clap(long("enable-debug-commands"))]
+    enable_debug_commands: bool,
+
     /// Disables the internal optimizations of the brainfuck program.
     /// Does not affect llvm optimization level.
     #[clap(short, long)]
     disable_opt: bool,
 
+    /// Comma-separated list of optimizer passes to apply, in order,
+    /// instead of the default optimizations.
+    #[clap(long, conflicts_with("disable-opt"))]
+    passes: Option<String>,
+
+    /// Print how long reading, lexing, parsing, optimizing and compiling or running the program took.
+    #[clap(long, conflicts_with_all(&["emit-tokens", "emit-ast", "emit-dot"]))]
+    time: bool,
+
+    /// Print how many nodes, loops and I/O instructions the program has, and how deep its loops nest,
+    /// both as parsed and once it has been optimized.
+    #[clap(long)]
+    stats: bool,
+
+    /// Print how many nodes the program had before and after each optimizer pass.
+    #[clap(long("opt-stats"), conflicts_with("disable-opt"))]
+    opt_stats: bool,
+
+    /// Don't warn about loops that certainly never end once entered, or the code after them.
+    #[clap(long("no-warnings"))]
+    no_warnings: bool,
+
+    /// Print the names of all available optimizer passes and exit.
+    #[clap(long("list-passes"))]
+    list_passes: bool,
+
     /// LLVM optimization level to use when compiling.
     /// Can be any of 0, 1, 2, 3.
     #[clap(short('O'), long, default_value = "2")]
     opt_level: u32,
 
+    /// Write LLVM bitcode and compile it with an external llc,
+    /// instead of emitting the object file directly.
+    #[clap(long("use-llc"))]
+    use_llc: bool,
+
+    /// The llc to compile bitcode with, implying --use-llc.
+    /// Defaults to the RUSTFUCK_LLC environment variable, or else the llc on the PATH.
+    /// It has to be from the same major LLVM version the bitcode is generated with.
+    #[clap(long)]
+    llc: Option<String>,
+
     /// Directory to store intermediate files in.
+    /// They are kept after compiling for debugging, unless --clean-int is given.
     #[clap(short('I'), long("int"), default_value = "./int/")]
     int_dir: String,
 
+    /// The linker to use instead of the default one for the target,
+    /// like ld.lld or mold in place of ld.
+    #[clap(long)]
+    linker: Option<String>,
+
+    /// An extra argument to pass to the linker. Can be given multiple times.
+    #[clap(long("linker-arg"), multiple_occurrences(true), number_of_values(1), allow_hyphen_values(true))]
+    linker_args: Vec<String>,
+
+    /// Compile to a position independent executable, which the loader can put at a random address.
+    /// Only for linux.
+    #[clap(long, conflicts_with("static-link"))]
+    pie: bool,
+
+    /// Link against the static C library, without a dynamic linker.
+    /// With glibc, this needs --entry main. Static C libraries like musl also work with the start entry point,
+    /// when their directory is given with --linker-arg -L<dir>.
+    #[clap(long("static"))]
+    static_link: bool,
+
+    /// Delete the intermediate files of this program once it has been linked.
+    /// Files of other programs in the same directory are left alone.
+    #[clap(long("clean-int"))]
+    clean_int: bool,
+
     /// The amount of time to sleep after each instruction when interpreting, in milliseconds
     #[clap(short('s'), long("slowdown"))]
     slow_down: Option<u32>,
+
+    /// Step through the program when interpreting, reading debugger commands from stdin.
+    /// The commands are step, continue, break <line> and print.
+    #[clap(long, requires("interpret"), conflicts_with("buffer-input"))]
+    debug: bool,
+
+    /// Read all of stdin at once when interpreting, instead of a byte at a time.
+    /// Much faster for programs reading lots of input, but unsuitable for interactive ones.
+    #[clap(long("buffer-input"))]
+    buffer_input: bool,
+
+    /// Read the input of the interpreted program from the file instead of stdin.
+    #[clap(long("input-file"), value_name("PATH"), conflicts_with("split-input"))]
+    input_file: Option<String>,
+
+    /// Write the output of the interpreted program to the file instead of stdout.
+    /// Whatever was written is kept when the program fails with an error.
+    #[clap(long("output-file"), value_name("PATH"))]
+    output_file: Option<String>,
+
+    /// Turn \r\n and lone \r in the input of the interpreted program into \n.
+    #[clap(long("normalize-input"))]
+    normalize_input: bool,
+
+    /// Split the source file at its first !, using what follows as the input when interpreting instead of stdin.
+    /// Compiled programs only get the part before it, and still read stdin.
+    #[clap(long("split-input"))]
+    split_input: bool,
+
+    /// Print the pointer and the first N cells of the tape after interpreting.
+    #[clap(long("dump-memory"), value_name("N"))]
+    dump_memory: Option<usize>,
+
+    /// Print how many cells of the tape hold each value after interpreting,
+    /// counting every cell up to the highest one the program touched.
+    #[clap(long("cell-histogram"))]
+    cell_histogram: bool,
+
+    /// The maximum number of instructions to execute when interpreting
+    /// before aborting the program.
+    #[clap(long("max-steps"))]
+    max_steps: Option<u64>,
+
+    /// The width of a single cell in bits.
+    /// Can be any of 8, 16, 32.
+    #[clap(long("cell-width"), default_value = "8")]
+    cell_width: CellWidth,
+
+    /// What reading input does to the current cell once the input is exhausted.
+    /// Can be any of unchanged, zero, -1.
+    #[clap(long("eof"), default_value = "unchanged", allow_hyphen_values(true))]
+    eof_policy: EofPolicy,
+
+    /// The operating system to compile for.
+    /// Can be any of linux, windows, wasm32. Wasm modules export a run function
+    /// and import putchar, getchar and write from the host.
+    #[clap(long("target"), default_value = "linux", conflicts_with("run"))]
+    target_os: TargetOs,
+
+    /// The entry point of compiled programs.
+    /// Can be any of start, main, library. With main, the program is linked with the C runtime using cc.
+    /// With library, the program becomes an int bf_run(void) function in an object file for linking into other programs,
+    /// which returns 0 when it finishes and -1 when it fails.
+    /// Windows programs always use main, wasm modules run.
+    #[clap(long("entry"), default_value = "start")]
+    entry_point: EntryPoint,
+
+    /// The number of cells on the tape when the program starts.
+    /// The tape still grows as needed beyond that.
+    #[clap(long("tape-size"), default_value = "30000")]
+    tape_size: usize,
+
+    /// Make the tape exactly --tape-size cells long when interpreting,
+    /// so moving past its end is an error instead of growing it.
+    #[clap(long("fixed-tape"))]
+    fixed_tape: bool,
+
+    /// Let the tape extend to the left of the starting cell when interpreting,
+    /// instead of failing with a pointer underflow.
+    #[clap(long)]
+    bidirectional: bool,
+
+    /// Wrap the pointer around the ends of the fixed size tape when interpreting,
+    /// instead of failing when it moves past them.
+    #[clap(long("wrap-pointer"), requires("fixed-tape"), conflicts_with("bidirectional"))]
+    wrap_pointer: bool,
+
+    /// Only store the cells the program touches when interpreting.
+    /// Much less memory for programs moving far out along the tape, but slower for everything else.
+    #[clap(long)]
+    sparse: bool,
+
+    /// Fail when an increment or decrement would wrap a cell around when interpreting.
+    /// Catches arithmetic bugs, but breaks programs that rely on wrapping on purpose.
+    #[clap(long("strict-cells"))]
+    strict_cells: bool,
+
+    /// The byte every byte of the tape starts out as, instead of zero.
+    /// A sentinel value helps catch programs reading cells they never wrote.
+    /// Not supported for wasm32 targets.
+    #[clap(long("init-cell"), default_value = "0")]
+    init_cell: u8,
+
+    /// Make compiled and interpreted programs exit with the value of the current cell once they finish.
+    /// Without this, they always exit with 0.
+    /// The interpreter exits with 125 on errors, which is reserved for them, so a program ending on a cell of 125
+    /// looks like it failed; compiled programs still exit with 0 on errors.
+    #[clap(long("exit-from-cell"))]
+    exit_from_cell: bool,
+
+    /// Emit debug information mapping the compiled program back to the brainfuck source.
+    #[clap(short('g'), long("debug-info"))]
+    debug_info: bool,
+
+    /// Generate cell operations inline instead of as calls to helper functions.
+    /// Produces faster code at the cost of larger executables.
+    #[clap(long("inline-ops"))]
+    inline_ops: bool,
+
+    /// Only flush the compiled program's output when it exits, instead of after every character.
+    /// Much faster for programs writing lots of output, but prompts may not show up before reading input.
+    #[clap(long("buffered-output"))]
+    buffered_output: bool,
+}
+impl Opts {
+    fn lexer_options(&self) -> LexerOptions {
+        LexerOptions {
+            tab_width: self.tab_width,
+            debug_commands: self.enable_debug_commands,
+            ..LexerOptions::default()
+        }
+    }
+    fn parser_options(&self) -> ParserOptions {
+        ParserOptions {
+            max_loop_depth: self.max_loop_depth,
+        }
+    }
+    fn interpreter_options(&self) -> InterpreterOptions {
+        InterpreterOptions {
+            sleep: self.slow_down,
+            eof_policy: self.eof_policy,
+            max_steps: self.max_steps,
+            tape_size: self.tape_size,
+            tape_bounds: if self.fixed_tape {
+                TapeBounds::Fixed(self.tape_size)
+            }
+            else {
+                TapeBounds::Growable
+            },
+            bidirectional: self.bidirectional,
+            sparse_tape: self.sparse,
+            pointer_mode: if self.wrap_pointer { PointerMode::Wrap } else { PointerMode::Linear },
+            strict_cells: self.strict_cells,
+            init_cell: self.init_cell,
+        }
+    }
+    fn compile_options(&self) -> CompileOptions {
+        CompileOptions {
+            output_path: self.output_path.as_ref().map(PathBuf::from),
+            program_stem: input_stem(self),
+            module_name: source_name(self),
+            int_dir: PathBuf::from(&self.int_dir),
+            opt_level: self.opt_level,
+            use_llc: self.use_llc || self.llc.is_some(),
+            llc: self.llc.clone()
+                .or_else(|| std::env::var(LLC_VAR).ok())
+                .map(PathBuf::from),
+            linker: self.linker.as_ref().map(PathBuf::from),
+            linker_args: self.linker_args.clone(),
+            static_link: self.static_link,
+            clean_int: self.clean_int,
+            codegen: self.codegen_options(),
+        }
+    }
+    fn codegen_options(&self) -> CodegenOptions {
+        CodegenOptions {
+            cell_width: self.cell_width,
+            eof_policy: self.eof_policy,
+            target_os: self.target_os,
+            entry_point: self.entry_point,
+            inline_ops: self.inline_ops,
+            tape_size: self.tape_size as u64,
+            debug_info: self.debug_info,
+            buffered_output: self.buffered_output,
+            init_cell: self.init_cell,
+            exit_from_cell: self.exit_from_cell,
+            pie: self.pie,
+        }
+    }
 }
@@ -1,58 +1,66 @@
 use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::optimizer::visitor::{Visitor, walk_children};
 
 
 
 pub fn collapse_next(program: &mut InstructionNode) {
-    match &mut program.node_type {
-        NodeType::Program(nodes) => collapse_node_list(nodes),
-        NodeType::Loop(nodes) => collapse_node_list(nodes),
-        _ => (),
-    }
+    CollapseNext.visit_node(program);
 }
 
 
-fn collapse_node_list(nodes: &mut Vec<InstructionNode>) {
-    let mut new_nodes = Vec::with_capacity(nodes.len());
+struct CollapseNext;
+impl Visitor for CollapseNext {
+    fn visit_children(&mut self, nodes: &mut Vec<InstructionNode>) {
+        let mut new_nodes = Vec::with_capacity(nodes.len());
 
 
-    let mut current_incr = None;
-    let mut current_line = 0;
-    let mut current_char = 0;
+        let mut current_incr: Option<usize> = None;
+        let mut current_line = 0;
+        let mut current_char = 0;
+        let mut current_start = 0;
+        let mut current_end = 0;
 
 
-    for mut node in nodes.split_off(0).into_iter() {
-        if let NodeType::Next(amount) = node.node_type {
-            match &mut current_incr {
-                Some(incr) => *incr += amount,
-                None => {
-                    current_incr = Some(amount);
-                    current_line = node.line;
-                    current_char = node.char;
+        for node in nodes.split_off(0).into_iter() {
+            if let NodeType::Next(amount) = node.node_type {
+                current_end = node.byte_end;
+                match &mut current_incr {
+                    // No tape is this long, so the move fails all the same when saturated.
+                    Some(incr) => *incr = incr.saturating_add(amount),
+                    None => {
+                        current_incr = Some(amount);
+                        current_line = node.line;
+                        current_char = node.char;
+                        current_start = node.byte_offset;
+                    }
                 }
             }
-        }
-        else {
-            if let Some(incr) = current_incr.take() {
-                new_nodes.push(InstructionNode {
-                    node_type: NodeType::Next(incr),
-                    line: current_line,
-                    char: current_char,
-                });
+            else {
+                if let Some(incr) = current_incr.take() {
+                    new_nodes.push(InstructionNode {
+                        node_type: NodeType::Next(incr),
+                        line: current_line,
+                        char: current_char,
+                        byte_offset: current_start,
+                        byte_end: current_end,
+                    });
+                }
+
+                new_nodes.push(node);
             }
+        }
+        if let Some(incr) = current_incr.take() {
+            new_nodes.push(InstructionNode {
+                node_type: NodeType::Next(incr),
+                line: current_line,
+                char: current_char,
+                byte_offset: current_start,
+                byte_end: current_end,
+            });
+        }
 
-            collapse_next(&mut node);
+        *nodes = new_nodes;
 
-            new_nodes.push(node);
-        }
-    }
-    if let Some(incr) = current_incr.take() {
-        new_nodes.push(InstructionNode {
-            node_type: NodeType::Next(incr),
-            line: current_line,
-            char: current_char,
-        });
+        walk_children(self, nodes);
     }
-
-    *nodes = new_nodes;
 }
-
@@ -15,19 +15,24 @@ fn collapse_node_list(nodes: &mut Vec<InstructionNode>) {
     let mut new_nodes = Vec::with_capacity(nodes.len());
 
 
-    let mut current_incr = None;
+    let mut current_incr: Option<usize> = None;
     let mut current_line = 0;
     let mut current_char = 0;
+    let mut current_start = 0;
+    let mut current_end = 0;
 
 
     for mut node in nodes.split_off(0).into_iter() {
         if let NodeType::Previous(amount) = node.node_type {
+            current_end = node.byte_end;
             match &mut current_incr {
-                Some(incr) => *incr += amount,
+                // Moving this far left underflows the pointer anyway, so there is no need to wrap around.
+                Some(incr) => *incr = incr.saturating_add(amount),
                 None => {
                     current_incr = Some(amount);
                     current_line = node.line;
                     current_char = node.char;
+                    current_start = node.byte_offset;
                 }
             }
         }
@@ -37,6 +42,8 @@ fn collapse_node_list(nodes: &mut Vec<InstructionNode>) {
                     node_type: NodeType::Previous(incr),
                     line: current_line,
                     char: current_char,
+                    byte_offset: current_start,
+                    byte_end: current_end,
                 });
             }
 
@@ -50,6 +57,8 @@ fn collapse_node_list(nodes: &mut Vec<InstructionNode>) {
             node_type: NodeType::Previous(incr),
             line: current_line,
             char: current_char,
+            byte_offset: current_start,
+            byte_end: current_end,
         });
     }
 
@@ -1,34 +1,33 @@
 use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::optimizer::visitor::{Visitor, walk_node};
 
 
 
 
+/// Replaces the clear loops [-] and [+] with a SetCell(0).
+/// Loops with larger steps like [--] or [++] are left alone,
+/// since they only terminate if the cell starts out even.
 pub fn collapse_set_zero(node: &mut InstructionNode) {
-    match &mut node.node_type {
-        NodeType::Program(children) => collapse_nodes(children),
-        NodeType::Loop(children) => collapse_nodes(children),
-        _ => (),
-    }
+    CollapseSetZero.visit_node(node);
 }
-fn collapse_nodes(nodes: &mut Vec<InstructionNode>) {
-    for node in nodes {
-        if let NodeType::Loop(children) = &node.node_type {
-            if children.len() == 1 {
-                if let NodeType::Decrement(1) = children[0].node_type {
-                    *node = InstructionNode {
-                        node_type: NodeType::SetCell(0),
-                        line: node.line,
-                        char: node.char,
-                    };
 
-                }
-            }
-            else {
-                collapse_set_zero(node);
+
+struct CollapseSetZero;
+impl Visitor for CollapseSetZero {
+    fn visit_node(&mut self, node: &mut InstructionNode) {
+        if let NodeType::Loop(children) = &node.node_type {
+            if children.len() == 1 && matches!(children[0].node_type, NodeType::Decrement(1) | NodeType::Increment(1)) {
+                *node = InstructionNode {
+                    node_type: NodeType::SetCell(0),
+                    line: node.line,
+                    char: node.char,
+                    byte_offset: node.byte_offset,
+                    byte_end: node.byte_end,
+                };
+                return;
             }
         }
-        else {
-            collapse_set_zero(node);
-        }
+
+        walk_node(self, node);
     }
 }
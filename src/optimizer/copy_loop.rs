@@ -0,0 +1,52 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::optimizer::multiply_loop::multiply_targets;
+
+
+
+
+/// Replaces loops like [->+<], which move the current cell onto another one,
+/// with a MoveAdd followed by a SetCell(0).
+/// This is the special case of a multiply loop with a single target and a factor of one,
+/// so it needs to run before collapse_multiply_loop.
+pub fn collapse_copy_loop(node: &mut InstructionNode) {
+    match &mut node.node_type {
+        NodeType::Program(children) => collapse_nodes(children),
+        NodeType::Loop(children) => collapse_nodes(children),
+        _ => (),
+    }
+}
+fn collapse_nodes(nodes: &mut Vec<InstructionNode>) {
+    let mut new_nodes = Vec::with_capacity(nodes.len());
+
+    for mut node in nodes.split_off(0).into_iter() {
+        if let Some(offset) = copy_offset(&node) {
+            new_nodes.push(InstructionNode {
+                node_type: NodeType::MoveAdd { offset },
+                line: node.line,
+                char: node.char,
+                byte_offset: node.byte_offset,
+                byte_end: node.byte_end,
+            });
+            new_nodes.push(InstructionNode {
+                node_type: NodeType::SetCell(0),
+                line: node.line,
+                char: node.char,
+                byte_offset: node.byte_offset,
+                byte_end: node.byte_end,
+            });
+        }
+        else {
+            collapse_copy_loop(&mut node);
+            new_nodes.push(node);
+        }
+    }
+
+    *nodes = new_nodes;
+}
+
+fn copy_offset(node: &InstructionNode) -> Option<isize> {
+    match multiply_targets(node)?.as_slice() {
+        &[(offset, 1)] => Some(offset),
+        _ => None,
+    }
+}
@@ -0,0 +1,73 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::optimizer::visitor::{Visitor, walk_children};
+use std::collections::HashSet;
+
+
+
+
+/// Removes writes to a cell that a later SetCell or SetAtOffset overwrites before anything reads it,
+/// so +>-<[-] only keeps the decrement of the second cell and the SetCell(0).
+/// Only straight-line runs of moves and cell writes are looked at, with the cells tracked by their offset
+/// from the pointer at the start of the run; any loop, I/O or other node ends the run, since it might read the cells.
+/// A removed write can't fail anymore, so an error it would have caused, like a cell overflow,
+/// is reported at the write that overwrites it instead.
+pub fn remove_dead_stores(program: &mut InstructionNode) {
+    RemoveDeadStores.visit_node(program);
+}
+
+
+struct RemoveDeadStores;
+impl Visitor for RemoveDeadStores {
+    fn visit_children(&mut self, nodes: &mut Vec<InstructionNode>) {
+        walk_children(self, nodes);
+
+        let offsets = run_offsets(nodes);
+        let mut is_dead = vec![false; nodes.len()];
+        // The offsets that get set later on in the current run, going backwards.
+        let mut overwritten = HashSet::new();
+
+        for (i, node) in nodes.iter().enumerate().rev() {
+            let offset = offsets[i];
+
+            match &node.node_type {
+                NodeType::Next(_) | NodeType::Previous(_) => (),
+                NodeType::Increment(_) | NodeType::Decrement(_) => is_dead[i] = overwritten.contains(&offset),
+                NodeType::AddAtOffset { offset: target, .. } => is_dead[i] = overwritten.contains(&(offset + target)),
+                NodeType::SetCell(_) => {
+                    is_dead[i] = !overwritten.insert(offset);
+                }
+                NodeType::SetAtOffset { offset: target, .. } => {
+                    is_dead[i] = !overwritten.insert(offset + target);
+                }
+                _ => overwritten.clear(),
+            }
+        }
+
+        let mut index = 0;
+        nodes.retain(|_| {
+            let keep = !is_dead[index];
+            index += 1;
+            keep
+        });
+    }
+}
+
+/// The offset of the pointer before each of the nodes, from where it was at the start of the run the node is in.
+fn run_offsets(nodes: &[InstructionNode]) -> Vec<isize> {
+    let mut offsets = Vec::with_capacity(nodes.len());
+    let mut offset = 0isize;
+
+    for node in nodes {
+        offsets.push(offset);
+
+        match &node.node_type {
+            NodeType::Next(amount) => offset += *amount as isize,
+            NodeType::Previous(amount) => offset -= *amount as isize,
+            NodeType::Increment(_) | NodeType::Decrement(_) | NodeType::SetCell(_) => (),
+            NodeType::AddAtOffset { .. } | NodeType::SetAtOffset { .. } => (),
+            _ => offset = 0,
+        }
+    }
+
+    offsets
+}
@@ -0,0 +1,61 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+
+
+
+
+/// Folds any increments and decrements directly following a SetCell into its value,
+/// so [-]+++ becomes a single SetCell(3).
+/// The value is kept modulo 2^32, which is the same cell value at every width since cells are at most 32 bits wide,
+/// so [-]- becomes SetCell(4294967295) rather than SetCell(usize::MAX); the backends reduce it further to the cell width.
+pub fn fold_set_arithmetic(program: &mut InstructionNode) {
+    match &mut program.node_type {
+        NodeType::Program(nodes) => fold_node_list(nodes),
+        NodeType::Loop(nodes) => fold_node_list(nodes),
+        _ => (),
+    }
+}
+
+
+fn fold_node_list(nodes: &mut Vec<InstructionNode>) {
+    let mut new_nodes: Vec<InstructionNode> = Vec::with_capacity(nodes.len());
+
+
+    for mut node in nodes.split_off(0).into_iter() {
+        let last_set = match new_nodes.last_mut() {
+            Some(InstructionNode { node_type: NodeType::SetCell(value), .. }) => Some(value),
+            _ => None,
+        };
+
+        match (last_set, &node.node_type) {
+            (Some(value), NodeType::Increment(amount)) => *value = value.wrapping_add(*amount) as u32 as usize,
+            (Some(value), NodeType::Decrement(amount)) => *value = value.wrapping_sub(*amount) as u32 as usize,
+            _ => {
+                fold_set_arithmetic(&mut node);
+
+                new_nodes.push(node);
+            }
+        }
+    }
+
+    *nodes = new_nodes;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_value_wraps_modulo_32_bits() {
+        let mut program = InstructionNode::new(NodeType::Program(vec![
+            InstructionNode::new(NodeType::SetCell(0)),
+            InstructionNode::new(NodeType::Decrement(1)),
+        ]));
+        fold_set_arithmetic(&mut program);
+
+        let expected = InstructionNode::new(NodeType::Program(vec![
+            InstructionNode::new(NodeType::SetCell(u32::MAX as usize)),
+        ]));
+        assert_eq!(program, expected);
+    }
+}
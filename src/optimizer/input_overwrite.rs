@@ -0,0 +1,41 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::optimizer::visitor::{Visitor, walk_children};
+
+
+
+
+/// Removes SetCell, Increment and Decrement nodes right before an Input, with no move in between,
+/// so the [-] of [-], goes away since the input overwrites the cell anyway.
+/// That is only true when reading past the end of the input changes the cell too,
+/// so this pass is wrong for EofPolicy::Unchanged, and isn't one of the default passes.
+/// A removed write can't fail anymore, so a cell overflow it would have caused isn't reported.
+pub fn remove_writes_before_input(program: &mut InstructionNode) {
+    RemoveWritesBeforeInput.visit_node(program);
+}
+
+
+struct RemoveWritesBeforeInput;
+impl Visitor for RemoveWritesBeforeInput {
+    fn visit_children(&mut self, nodes: &mut Vec<InstructionNode>) {
+        walk_children(self, nodes);
+
+        let mut is_dead = vec![false; nodes.len()];
+        // Whether the current cell is overwritten by an Input before anything else happens to it, going backwards.
+        let mut overwritten = false;
+
+        for (i, node) in nodes.iter().enumerate().rev() {
+            match &node.node_type {
+                NodeType::Input => overwritten = true,
+                NodeType::SetCell(_) | NodeType::Increment(_) | NodeType::Decrement(_) => is_dead[i] = overwritten,
+                _ => overwritten = false,
+            }
+        }
+
+        let mut index = 0;
+        nodes.retain(|_| {
+            let keep = !is_dead[index];
+            index += 1;
+            keep
+        });
+    }
+}
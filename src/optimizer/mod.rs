@@ -1,51 +1,359 @@
 use crate::front_end::parser::InstructionNode;
+use std::fmt::{Display, Formatter};
 use remove_comment_loop::remove_comment_loop;
 use collapse_increments::collapse_increments;
 use collapse_decrements::collapse_decrements;
 use collapse_next::collapse_next;
 use collapse_previous::collapse_previous;
 use collapse_set_zero::collapse_set_zero;
+use remove_dead_loop::remove_dead_loop;
+use net_delta::collapse_net_delta;
+use net_move::collapse_net_move;
+use remove_noops::remove_noops;
+use copy_loop::collapse_copy_loop;
+use multiply_loop::collapse_multiply_loop;
+use scan_loop::collapse_scan_loop;
+use fold_set::fold_set_arithmetic;
+use overwritten_set::remove_overwritten_set;
+use offset_ops::fuse_offset_ops;
+use dead_store::remove_dead_stores;
+use remove_empty_loop::remove_empty_loop;
+use static_prefix::fold_static_prefix;
+use remove_unreachable::remove_unreachable;
+use input_overwrite::remove_writes_before_input;
 
-pub mod remove_comment_loop;
-pub mod collapse_increments;
-pub mod collapse_decrements;
-pub mod collapse_next;
-pub mod collapse_previous;
-pub mod collapse_set_zero;
+mod remove_comment_loop;
+mod collapse_increments;
+mod collapse_decrements;
+mod collapse_next;
+mod collapse_previous;
+mod collapse_set_zero;
+mod remove_dead_loop;
+mod net_delta;
+mod net_move;
+mod remove_noops;
+mod copy_loop;
+mod multiply_loop;
+mod scan_loop;
+mod fold_set;
+mod overwritten_set;
+mod offset_ops;
+mod dead_store;
+mod remove_empty_loop;
+mod static_prefix;
+mod remove_unreachable;
+mod input_overwrite;
+pub mod visitor;
 
 pub type OptimizerPass = fn(&mut InstructionNode);
+/// A pass that can carry settings or state along with it, for passes added with with_pass or with_boxed_pass.
+pub type BoxedPass = Box<dyn FnMut(&mut InstructionNode)>;
 
 
 
+/// All available passes by name, in the order the default optimizations apply them.
+pub const PASSES: &[(&str, OptimizerPass)] = &[
+    ("remove_comment_loop", remove_comment_loop),
+    ("collapse_increments", collapse_increments),
+    ("collapse_decrements", collapse_decrements),
+    ("collapse_next", collapse_next),
+    ("collapse_previous", collapse_previous),
+    ("collapse_net_delta", collapse_net_delta),
+    ("collapse_net_move", collapse_net_move),
+    ("remove_noops", remove_noops),
+    ("collapse_set_zero", collapse_set_zero),
+    ("remove_dead_loop", remove_dead_loop),
+    ("collapse_copy_loop", collapse_copy_loop),
+    ("collapse_multiply_loop", collapse_multiply_loop),
+    ("collapse_scan_loop", collapse_scan_loop),
+    ("fold_set_arithmetic", fold_set_arithmetic),
+    ("remove_overwritten_set", remove_overwritten_set),
+    ("fuse_offset_ops", fuse_offset_ops),
+    ("remove_dead_stores", remove_dead_stores),
+    ("remove_empty_loop", remove_empty_loop),
+    ("fold_static_prefix", fold_static_prefix),
+];
+
+/// Passes that are only applied when asked for by name, since they are too situational to run by default.
+pub const OPTIONAL_PASSES: &[(&str, OptimizerPass)] = &[
+    ("remove_unreachable", remove_unreachable),
+    ("remove_writes_before_input", remove_writes_before_input),
+];
+
+/// The passes relying on every cell starting out as zero,
+/// which have to be left out for programs running on a tape filled with anything else.
+pub const ZERO_TAPE_PASSES: &[&str] = &["remove_comment_loop", "remove_empty_loop", "fold_static_prefix", "remove_unreachable"];
+
+/// The passes telling cells apart by their offset from the pointer,
+/// which have to be left out when the pointer wraps around the tape, where different offsets can be the same cell.
+pub const LINEAR_TAPE_PASSES: &[&str] = &["fold_static_prefix", "remove_empty_loop", "remove_dead_stores", "remove_unreachable"];
+
+/// The passes doing cell arithmetic at compile time with wrapping, or dropping writes that might wrap,
+/// which have to be left out when cells must fail instead of wrapping around, so the failure still happens.
+/// collapse_set_zero is among them for [+], which wraps the cell around to reach zero.
+pub const WRAPPING_PASSES: &[&str] = &[
+    "collapse_net_delta",
+    "collapse_set_zero",
+    "fold_set_arithmetic",
+    "remove_dead_stores",
+    "fold_static_prefix",
+    "remove_writes_before_input",
+];
+
+pub fn find_pass(name: &str) -> Option<OptimizerPass> {
+    find_named_pass(name).map(|(_, pass)| pass)
+}
+fn find_named_pass(name: &str) -> Option<(&'static str, OptimizerPass)> {
+    PASSES.iter()
+        .chain(OPTIONAL_PASSES)
+        .find(|(pass_name, _)| *pass_name == name)
+        .copied()
+}
+
+
 pub fn apply_default_optimizations(program: &mut InstructionNode) {
-    Optimizer::new()
-        .with_pass(remove_comment_loop)
-        .with_pass(collapse_increments)
-        .with_pass(collapse_decrements)
-        .with_pass(collapse_next)
-        .with_pass(collapse_previous)
-        .with_pass(collapse_set_zero)
-        .apply(program);
+    default_optimizer().apply_fixpoint(program, DEFAULT_MAX_ITERATIONS);
 }
 
+/// An optimizer with all passes, in the order the default optimizations apply them.
+pub fn default_optimizer() -> Optimizer {
+    let mut optimizer = Optimizer::new();
+    for &(name, pass) in PASSES {
+        optimizer = optimizer.with_pass(name, pass);
+    }
+
+    optimizer
+}
+
+/// How often the default passes are rerun at most,
+/// in case they keep finding new opportunities for each other.
+pub const DEFAULT_MAX_ITERATIONS: usize = 8;
+
 
 
 pub struct Optimizer {
-    passes: Vec<OptimizerPass>,
+    passes: Vec<(&'static str, BoxedPass)>,
+    record_stats: bool,
+}
+impl Default for Optimizer {
+    fn default() -> Self {
+        Optimizer::new()
+    }
 }
 impl Optimizer {
     pub fn new() -> Optimizer {
         Optimizer {
             passes: Vec::new(),
+            record_stats: false,
         }
     }
-    pub fn with_pass(mut self, pass: OptimizerPass) -> Optimizer {
-        self.passes.push(pass);
+    /// Adds a pass to apply after all the ones added before it.
+    /// Besides the plain functions in PASSES, the pass can be a closure capturing its own settings or state,
+    /// which carries over between the rounds of apply_fixpoint.
+    pub fn with_pass<P>(self, name: &'static str, pass: P) -> Optimizer
+        where P: FnMut(&mut InstructionNode) + 'static,
+    {
+        self.with_boxed_pass(name, Box::new(pass))
+    }
+    pub fn with_boxed_pass(mut self, name: &'static str, pass: BoxedPass) -> Optimizer {
+        self.passes.push((name, pass));
         self
     }
-    pub fn apply(self, program: &mut InstructionNode) {
-        for pass in self.passes {
-            pass(program);
+    /// Leaves out the passes with any of the names, like ZERO_TAPE_PASSES.
+    pub fn without_passes(mut self, names: &[&str]) -> Optimizer {
+        self.passes.retain(|(name, _)| !names.contains(name));
+        self
+    }
+    /// Makes apply and apply_fixpoint count the nodes of the program before and after each pass.
+    /// Without this, the stats they return are empty.
+    pub fn with_stats(mut self) -> Optimizer {
+        self.record_stats = true;
+        self
+    }
+    /// Builds an optimizer applying the named passes in the given order.
+    /// Fails with the first name that doesn't belong to any pass.
+    pub fn from_names<'a, I>(names: I) -> Result<Optimizer, String>
+        where I: IntoIterator<Item = &'a str>,
+    {
+        let mut optimizer = Optimizer::new();
+
+        for name in names {
+            match find_named_pass(name) {
+                Some((name, pass)) => optimizer = optimizer.with_pass(name, pass),
+                None => return Err(name.to_string()),
+            }
+        }
+
+        Ok(optimizer)
+    }
+    pub fn apply(mut self, program: &mut InstructionNode) -> OptimizerStats {
+        let mut stats = OptimizerStats::default();
+        self.apply_passes(program, 1, &mut stats);
+        stats
+    }
+    /// Applies all passes repeatedly, until the program stops changing
+    /// or they have been applied max_iters times.
+    pub fn apply_fixpoint(mut self, program: &mut InstructionNode, max_iters: usize) -> OptimizerStats {
+        let mut stats = OptimizerStats::default();
+
+        for iteration in 1..=max_iters {
+            let before = program.clone();
+
+            self.apply_passes(program, iteration, &mut stats);
+
+            if *program == before {
+                break;
+            }
+        }
+
+        stats
+    }
+
+    fn apply_passes(&mut self, program: &mut InstructionNode, iteration: usize, stats: &mut OptimizerStats) {
+        for (name, pass) in &mut self.passes {
+            if self.record_stats {
+                let nodes_before = program.node_count();
+                pass(program);
+                stats.passes.push(PassStats {
+                    name,
+                    iteration,
+                    nodes_before,
+                    nodes_after: program.node_count(),
+                });
+            }
+            else {
+                pass(program);
+            }
+        }
+    }
+}
+
+
+/// The effect each pass had on the size of the program, in the order they were applied.
+#[derive(Clone, Debug, Default)]
+pub struct OptimizerStats {
+    pub passes: Vec<PassStats>,
+}
+#[derive(Copy, Clone, Debug)]
+pub struct PassStats {
+    pub name: &'static str,
+    /// Which round of apply_fixpoint the pass was applied in, starting at 1.
+    pub iteration: usize,
+    pub nodes_before: usize,
+    pub nodes_after: usize,
+}
+impl Display for OptimizerStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name_len = self.passes.iter().map(|pass| pass.name.len() + 1).max().unwrap_or(0);
+        let iterations = self.passes.iter().map(|pass| pass.iteration).max().unwrap_or(0);
+
+        let mut current_iteration = 0;
+        for pass in &self.passes {
+            if iterations > 1 && pass.iteration != current_iteration {
+                current_iteration = pass.iteration;
+                writeln!(f, "Iteration {}:", current_iteration)?;
+            }
+
+            writeln!(
+                f,
+                "{:<nw$} {} -> {} nodes",
+                format!("{}:", pass.name), pass.nodes_before, pass.nodes_after, nw = name_len,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::front_end::lexer::lex;
+    use crate::front_end::parser::{parse, NodeType};
+    use crate::interpreter::{interpret, InterpreterOptions, InterpretationError, SliceSource, VecWriter, TapeBounds, PointerMode};
+
+    fn run(src: &str, optimizer: Option<Optimizer>, options: &InterpreterOptions) -> Result<Vec<u8>, InterpretationError> {
+        let mut program = parse(&lex(src)).unwrap();
+        if let Some(optimizer) = optimizer {
+            optimizer.apply_fixpoint(&mut program, DEFAULT_MAX_ITERATIONS);
+        }
+
+        let mut out = VecWriter(Vec::new());
+        interpret::<u8, _, _>(&program, &mut out, &mut SliceSource::new(Vec::new()), options)?;
+        Ok(out.0)
+    }
+
+    #[test]
+    fn linear_tape_passes_keep_wrapped_offsets_apart() {
+        let options = InterpreterOptions {
+            tape_bounds: TapeBounds::Fixed(4),
+            pointer_mode: PointerMode::Wrap,
+            ..InterpreterOptions::default()
+        };
+        let src = "+>>>>+<<<<.";
+
+        assert_eq!(run(src, None, &options).unwrap(), vec![2]);
+        let optimizer = default_optimizer().without_passes(LINEAR_TAPE_PASSES);
+        assert_eq!(run(src, Some(optimizer), &options).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn zero_tape_passes_keep_empty_loop_on_filled_tape() {
+        let options = InterpreterOptions {
+            init_cell: 5,
+            max_steps: Some(10_000),
+            ..InterpreterOptions::default()
+        };
+        let src = "[].";
+
+        let is_hanging = |result| matches!(result, Err(InterpretationError::StepLimitExceeded { .. }));
+        assert!(is_hanging(run(src, None, &options)));
+        let optimizer = default_optimizer().without_passes(ZERO_TAPE_PASSES);
+        assert!(is_hanging(run(src, Some(optimizer), &options)));
+    }
+
+    #[test]
+    fn wrapping_passes_keep_strict_cell_overflows() {
+        let options = InterpreterOptions { strict_cells: true, ..InterpreterOptions::default() };
+        let src = "[-]+[->-<]>.";
+
+        let is_overflow = |result| matches!(result, Err(InterpretationError::CellOverflow { .. }));
+        assert!(is_overflow(run(src, None, &options)));
+        let optimizer = default_optimizer().without_passes(WRAPPING_PASSES);
+        assert!(is_overflow(run(src, Some(optimizer), &options)));
+    }
+
+    #[test]
+    fn net_move_keeps_saturated_moves() {
+        let mut program = InstructionNode::new(NodeType::Program(vec![
+            InstructionNode::new(NodeType::Next(usize::MAX)),
+            InstructionNode::new(NodeType::Previous(1)),
+        ]));
+        collapse_net_move(&mut program);
+
+        let node_types: Vec<_> = match &program.node_type {
+            NodeType::Program(nodes) => nodes.iter().map(|node| node.node_type.clone()).collect(),
+            _ => unreachable!(),
+        };
+        assert_eq!(node_types, vec![NodeType::Next(usize::MAX), NodeType::Previous(1)]);
+    }
+
+    #[test]
+    fn static_prefix_stops_at_far_moves() {
+        for &far in &[usize::MAX, 100_000] {
+            let mut program = InstructionNode::new(NodeType::Program(vec![
+                InstructionNode::new(NodeType::Next(far)),
+                InstructionNode::new(NodeType::Next(1)),
+                InstructionNode::new(NodeType::Increment(1)),
+            ]));
+            fold_static_prefix(&mut program);
+
+            let node_types: Vec<_> = match &program.node_type {
+                NodeType::Program(nodes) => nodes.iter().map(|node| node.node_type.clone()).collect(),
+                _ => unreachable!(),
+            };
+            assert_eq!(node_types, vec![NodeType::Next(far), NodeType::Next(1), NodeType::Increment(1)]);
         }
     }
 }
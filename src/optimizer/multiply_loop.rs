@@ -0,0 +1,81 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use std::collections::BTreeMap;
+
+
+
+
+/// Replaces loops like [->++>+++<<] with a MultiplyAdd followed by a SetCell(0).
+/// Only loops made up entirely of pointer moves and arithmetic are considered,
+/// and only if the pointer ends up where it started
+/// and the current cell is decremented by exactly one per iteration.
+pub fn collapse_multiply_loop(node: &mut InstructionNode) {
+    match &mut node.node_type {
+        NodeType::Program(children) => collapse_nodes(children),
+        NodeType::Loop(children) => collapse_nodes(children),
+        _ => (),
+    }
+}
+fn collapse_nodes(nodes: &mut Vec<InstructionNode>) {
+    let mut new_nodes = Vec::with_capacity(nodes.len());
+
+    for mut node in nodes.split_off(0).into_iter() {
+        if let Some(targets) = multiply_targets(&node) {
+            if !targets.is_empty() {
+                new_nodes.push(InstructionNode {
+                    node_type: NodeType::MultiplyAdd { targets },
+                    line: node.line,
+                    char: node.char,
+                    byte_offset: node.byte_offset,
+                    byte_end: node.byte_end,
+                });
+            }
+            new_nodes.push(InstructionNode {
+                node_type: NodeType::SetCell(0),
+                line: node.line,
+                char: node.char,
+                byte_offset: node.byte_offset,
+                byte_end: node.byte_end,
+            });
+        }
+        else {
+            collapse_multiply_loop(&mut node);
+            new_nodes.push(node);
+        }
+    }
+
+    *nodes = new_nodes;
+}
+
+/// Returns the offsets and factors a loop adds the current cell to,
+/// if the node is a loop that collapse_multiply_loop can replace.
+pub(crate) fn multiply_targets(node: &InstructionNode) -> Option<Vec<(isize, i32)>> {
+    let children = match &node.node_type {
+        NodeType::Loop(children) => children,
+        _ => return None,
+    };
+
+    let mut offset: isize = 0;
+    let mut deltas = BTreeMap::new();
+
+    for child in children {
+        match child.node_type {
+            NodeType::Next(amount) => offset += amount as isize,
+            NodeType::Previous(amount) => offset -= amount as isize,
+            NodeType::Increment(amount) => {
+                let delta = deltas.entry(offset).or_insert(0i32);
+                *delta = delta.wrapping_add(amount as i32);
+            }
+            NodeType::Decrement(amount) => {
+                let delta = deltas.entry(offset).or_insert(0i32);
+                *delta = delta.wrapping_sub(amount as i32);
+            }
+            _ => return None,
+        }
+    }
+
+    if offset != 0 || deltas.remove(&0) != Some(-1) {
+        return None;
+    }
+
+    Some(deltas.into_iter().filter(|&(_, factor)| factor != 0).collect())
+}
@@ -0,0 +1,83 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+
+
+
+
+/// Merges runs of increments and decrements into a single node with their net effect,
+/// so +++-- becomes Increment(1). A run that cancels out completely, like +-, is removed.
+/// This should run after collapse_increments and collapse_decrements.
+pub fn collapse_net_delta(program: &mut InstructionNode) {
+    match &mut program.node_type {
+        NodeType::Program(nodes) => collapse_node_list(nodes),
+        NodeType::Loop(nodes) => collapse_node_list(nodes),
+        _ => (),
+    }
+}
+
+
+fn collapse_node_list(nodes: &mut Vec<InstructionNode>) {
+    let mut new_nodes = Vec::with_capacity(nodes.len());
+
+
+    let mut current_delta: Option<isize> = None;
+    let mut current_line = 0;
+    let mut current_char = 0;
+    let mut current_start = 0;
+    let mut current_end = 0;
+
+
+    for mut node in nodes.split_off(0).into_iter() {
+        let delta = match node.node_type {
+            NodeType::Increment(amount) => Some(amount as isize),
+            NodeType::Decrement(amount) => Some((amount as isize).wrapping_neg()),
+            _ => None,
+        };
+
+        if let Some(delta) = delta {
+            current_end = node.byte_end;
+            match &mut current_delta {
+                Some(total) => *total = total.wrapping_add(delta),
+                None => {
+                    current_delta = Some(delta);
+                    current_line = node.line;
+                    current_char = node.char;
+                    current_start = node.byte_offset;
+                }
+            }
+        }
+        else {
+            if let Some(total) = current_delta.take() {
+                push_delta(&mut new_nodes, total, current_line, current_char, current_start, current_end);
+            }
+
+            collapse_net_delta(&mut node);
+
+            new_nodes.push(node);
+        }
+    }
+    if let Some(total) = current_delta.take() {
+        push_delta(&mut new_nodes, total, current_line, current_char, current_start, current_end);
+    }
+
+    *nodes = new_nodes;
+}
+
+fn push_delta(nodes: &mut Vec<InstructionNode>, delta: isize, line: u32, char: u32, byte_offset: usize, byte_end: usize) {
+    let node_type = if delta > 0 {
+        NodeType::Increment(delta as usize)
+    }
+    else if delta < 0 {
+        NodeType::Decrement(delta.wrapping_neg() as usize)
+    }
+    else {
+        return;
+    };
+
+    nodes.push(InstructionNode {
+        node_type,
+        line,
+        char,
+        byte_offset,
+        byte_end,
+    });
+}
@@ -0,0 +1,92 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use std::convert::TryFrom;
+
+
+
+
+/// Merges runs of pointer moves into a single move by their net offset,
+/// so >>< becomes Next(1). A run that cancels out completely, like ><, is removed.
+/// Note that a run which only dips below the first cell temporarily, like <> at the start,
+/// no longer causes a pointer underflow once collapsed.
+pub fn collapse_net_move(program: &mut InstructionNode) {
+    match &mut program.node_type {
+        NodeType::Program(nodes) => collapse_node_list(nodes),
+        NodeType::Loop(nodes) => collapse_node_list(nodes),
+        _ => (),
+    }
+}
+
+
+fn collapse_node_list(nodes: &mut Vec<InstructionNode>) {
+    let mut new_nodes = Vec::with_capacity(nodes.len());
+
+
+    let mut current_offset: Option<isize> = None;
+    let mut current_line = 0;
+    let mut current_char = 0;
+    let mut current_start = 0;
+    let mut current_end = 0;
+
+
+    for mut node in nodes.split_off(0).into_iter() {
+        // Moves too far to fit an offset, like saturated ones, are kept as they are and end the run.
+        let offset = match node.node_type {
+            NodeType::Next(amount) => isize::try_from(amount).ok(),
+            NodeType::Previous(amount) => isize::try_from(amount).ok().map(|amount| -amount),
+            _ => None,
+        };
+        let merged = match (current_offset, offset) {
+            (Some(total), Some(offset)) => total.checked_add(offset),
+            _ => None,
+        };
+
+        if let Some(merged) = merged {
+            current_end = node.byte_end;
+            current_offset = Some(merged);
+        }
+        else if let Some(offset) = offset {
+            if let Some(total) = current_offset.take() {
+                push_move(&mut new_nodes, total, current_line, current_char, current_start, current_end);
+            }
+            current_offset = Some(offset);
+            current_line = node.line;
+            current_char = node.char;
+            current_start = node.byte_offset;
+            current_end = node.byte_end;
+        }
+        else {
+            if let Some(total) = current_offset.take() {
+                push_move(&mut new_nodes, total, current_line, current_char, current_start, current_end);
+            }
+
+            collapse_net_move(&mut node);
+
+            new_nodes.push(node);
+        }
+    }
+    if let Some(total) = current_offset.take() {
+        push_move(&mut new_nodes, total, current_line, current_char, current_start, current_end);
+    }
+
+    *nodes = new_nodes;
+}
+
+fn push_move(nodes: &mut Vec<InstructionNode>, offset: isize, line: u32, char: u32, byte_offset: usize, byte_end: usize) {
+    let node_type = if offset > 0 {
+        NodeType::Next(offset as usize)
+    }
+    else if offset < 0 {
+        NodeType::Previous(offset.wrapping_neg() as usize)
+    }
+    else {
+        return;
+    };
+
+    nodes.push(InstructionNode {
+        node_type,
+        line,
+        char,
+        byte_offset,
+        byte_end,
+    });
+}
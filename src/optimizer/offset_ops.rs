@@ -0,0 +1,67 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+
+
+
+
+/// Fuses a pointer move followed by an increment, decrement or set
+/// into an AddAtOffset or SetAtOffset, with the move itself pushed behind it.
+/// So >+ becomes AddAtOffset(1, 1), Next(1), which lets collapse_net_move merge the move with later ones,
+/// until >+<- ends up as AddAtOffset(1, 1), Decrement(1) without moving the pointer at all.
+/// Offset operations already following a move are pushed in front of it the same way.
+pub fn fuse_offset_ops(program: &mut InstructionNode) {
+    match &mut program.node_type {
+        NodeType::Program(nodes) => collapse_node_list(nodes),
+        NodeType::Loop(nodes) => collapse_node_list(nodes),
+        _ => (),
+    }
+}
+
+
+fn collapse_node_list(nodes: &mut Vec<InstructionNode>) {
+    let mut new_nodes: Vec<InstructionNode> = Vec::with_capacity(nodes.len());
+
+    for mut node in nodes.split_off(0).into_iter() {
+        let pending_move = new_nodes.last().and_then(|last| match last.node_type {
+            NodeType::Next(amount) => Some(amount as isize),
+            NodeType::Previous(amount) => Some((amount as isize).wrapping_neg()),
+            _ => None,
+        });
+
+        let fused = match (pending_move, &node.node_type) {
+            (Some(offset), NodeType::Increment(amount)) => Some(NodeType::AddAtOffset {
+                offset,
+                amount: *amount as i32,
+            }),
+            (Some(offset), NodeType::Decrement(amount)) => Some(NodeType::AddAtOffset {
+                offset,
+                amount: (*amount as i32).wrapping_neg(),
+            }),
+            (Some(offset), NodeType::SetCell(value)) => Some(NodeType::SetAtOffset {
+                offset,
+                value: *value,
+            }),
+            (Some(offset), NodeType::AddAtOffset { offset: inner, amount }) => Some(NodeType::AddAtOffset {
+                offset: offset.wrapping_add(*inner),
+                amount: *amount,
+            }),
+            (Some(offset), NodeType::SetAtOffset { offset: inner, value }) => Some(NodeType::SetAtOffset {
+                offset: offset.wrapping_add(*inner),
+                value: *value,
+            }),
+            _ => None,
+        };
+
+        if let Some(node_type) = fused {
+            let pointer_move = new_nodes.pop().unwrap();
+            node.node_type = node_type;
+            new_nodes.push(node);
+            new_nodes.push(pointer_move);
+        }
+        else {
+            fuse_offset_ops(&mut node);
+            new_nodes.push(node);
+        }
+    }
+
+    *nodes = new_nodes;
+}
@@ -0,0 +1,36 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::optimizer::visitor::{Visitor, walk_children};
+
+
+
+
+/// Drops any SetCell directly followed by another SetCell, since the second one overwrites it
+/// before anything could see the value. After fold_set_arithmetic, this turns [-]+++[-] into a single SetCell(0).
+/// Only directly adjacent nodes count; any move, loop or I/O in between keeps both.
+pub fn remove_overwritten_set(program: &mut InstructionNode) {
+    RemoveOverwrittenSet.visit_node(program);
+}
+
+
+struct RemoveOverwrittenSet;
+impl Visitor for RemoveOverwrittenSet {
+    fn visit_children(&mut self, nodes: &mut Vec<InstructionNode>) {
+        walk_children(self, nodes);
+
+        let mut new_nodes: Vec<InstructionNode> = Vec::with_capacity(nodes.len());
+
+        for node in nodes.split_off(0).into_iter() {
+            let overwrites_last = matches!(
+                (new_nodes.last().map(|last| &last.node_type), &node.node_type),
+                (Some(NodeType::SetCell(_)), NodeType::SetCell(_))
+            );
+            if overwrites_last {
+                new_nodes.pop();
+            }
+
+            new_nodes.push(node);
+        }
+
+        *nodes = new_nodes;
+    }
+}
@@ -11,7 +11,7 @@ use crate::front_end::parser::{InstructionNode, NodeType};
 /// This absolutely needs to be the first pass applied, or it might destroy actually relevant loops.
 pub fn remove_comment_loop(program: &mut InstructionNode) {
     if let NodeType::Program(nodes) = &mut program.node_type {
-        while nodes.len() != 0 &&
+        while !nodes.is_empty() &&
             matches!(nodes[0].node_type, NodeType::Loop(_)) {
             nodes.remove(0);
         }
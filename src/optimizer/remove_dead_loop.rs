@@ -0,0 +1,36 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::optimizer::visitor::{Visitor, walk_children};
+
+
+
+
+/// Removes loops directly following a SetCell(0), since the cell they test is always zero,
+/// so they can never be entered.
+/// Needs collapse_set_zero to have run first, to find anything to remove.
+pub fn remove_dead_loop(program: &mut InstructionNode) {
+    RemoveDeadLoop.visit_node(program);
+}
+
+
+struct RemoveDeadLoop;
+impl Visitor for RemoveDeadLoop {
+    fn visit_children(&mut self, nodes: &mut Vec<InstructionNode>) {
+        let mut new_nodes: Vec<InstructionNode> = Vec::with_capacity(nodes.len());
+
+        for node in nodes.split_off(0).into_iter() {
+            let follows_zero = new_nodes.last()
+                .map(|prev| prev.node_type == NodeType::SetCell(0))
+                .unwrap_or(false);
+
+            if follows_zero && matches!(node.node_type, NodeType::Loop(_)) {
+                continue;
+            }
+
+            new_nodes.push(node);
+        }
+
+        *nodes = new_nodes;
+
+        walk_children(self, nodes);
+    }
+}
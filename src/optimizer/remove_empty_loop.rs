@@ -0,0 +1,62 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::optimizer::visitor::{Visitor, walk_children};
+
+
+
+
+/// Removes empty loops [] wherever the current cell is known to be zero, so they would be skipped anyway.
+/// That is the case at the start of the program, right after another loop,
+/// and after instructions that always leave the current cell at zero, like SetCell(0).
+/// An empty loop anywhere else is kept, since the cell might be nonzero,
+/// in which case the loop never terminates and the program has to hang just the same.
+pub fn remove_empty_loop(program: &mut InstructionNode) {
+    RemoveEmptyLoop.visit_node(program);
+}
+
+
+struct RemoveEmptyLoop;
+impl RemoveEmptyLoop {
+    fn remove_in(&mut self, nodes: &mut Vec<InstructionNode>, starts_zero: bool) {
+        walk_children(self, nodes);
+
+        let mut new_nodes = Vec::with_capacity(nodes.len());
+        let mut cell_is_zero = starts_zero;
+
+        for node in nodes.split_off(0).into_iter() {
+            if cell_is_zero && is_empty_loop(&node) {
+                continue;
+            }
+
+            cell_is_zero = match &node.node_type {
+                NodeType::Loop(_) => true,
+                NodeType::SetCell(value) => *value == 0,
+                NodeType::ScanRight(_) | NodeType::ScanLeft(_) => true,
+                NodeType::Output | NodeType::Debug => cell_is_zero,
+                NodeType::MultiplyAdd { .. } | NodeType::MoveAdd { .. } => cell_is_zero,
+                NodeType::AddAtOffset { offset, .. } | NodeType::SetAtOffset { offset, .. } => cell_is_zero && *offset != 0,
+                _ => false,
+            };
+
+            new_nodes.push(node);
+        }
+
+        *nodes = new_nodes;
+    }
+}
+impl Visitor for RemoveEmptyLoop {
+    fn visit_node(&mut self, node: &mut InstructionNode) {
+        match &mut node.node_type {
+            NodeType::Program(nodes) => self.remove_in(nodes, true),
+            NodeType::Loop(nodes) => self.remove_in(nodes, false),
+            _ => (),
+        }
+    }
+}
+
+
+fn is_empty_loop(node: &InstructionNode) -> bool {
+    match &node.node_type {
+        NodeType::Loop(children) => children.is_empty(),
+        _ => false,
+    }
+}
@@ -0,0 +1,30 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::optimizer::visitor::{Visitor, walk_children};
+
+
+
+
+/// Removes nodes that do nothing because their amount is zero, like Increment(0), Previous(0)
+/// or an AddAtOffset adding 0, wherever they come from.
+/// Amounts that only wrap around to zero for some cell widths, like Increment(256), are kept.
+pub fn remove_noops(program: &mut InstructionNode) {
+    RemoveNoops.visit_node(program);
+}
+
+
+struct RemoveNoops;
+impl Visitor for RemoveNoops {
+    fn visit_children(&mut self, nodes: &mut Vec<InstructionNode>) {
+        walk_children(self, nodes);
+        nodes.retain(|node| !is_noop(node));
+    }
+}
+
+fn is_noop(node: &InstructionNode) -> bool {
+    match &node.node_type {
+        NodeType::Increment(amount) | NodeType::Decrement(amount) => *amount == 0,
+        NodeType::Next(amount) | NodeType::Previous(amount) => *amount == 0,
+        NodeType::AddAtOffset { amount, .. } => *amount == 0,
+        _ => false,
+    }
+}
@@ -0,0 +1,36 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+use crate::optimizer::visitor::{Visitor, walk_children};
+use crate::analysis::first_unreachable;
+
+
+
+
+/// Drops everything after a loop that is certainly entered and never terminates,
+/// like the `.` in `+[>+<].`, since the program hangs in the loop before ever getting there.
+/// Which loops never terminate is undecidable in general, so this only catches the easy cases
+/// find_warnings warns about, and is left out of the default optimizations.
+/// Relies on the tape starting out as zero, to know the current cell at the start of the program.
+pub fn remove_unreachable(program: &mut InstructionNode) {
+    RemoveUnreachable.visit_node(program);
+}
+
+
+struct RemoveUnreachable;
+impl RemoveUnreachable {
+    fn remove_in(&mut self, nodes: &mut Vec<InstructionNode>, starts_zero: bool) {
+        if let Some(first) = first_unreachable(nodes, starts_zero) {
+            nodes.truncate(first);
+        }
+
+        walk_children(self, nodes);
+    }
+}
+impl Visitor for RemoveUnreachable {
+    fn visit_node(&mut self, node: &mut InstructionNode) {
+        match &mut node.node_type {
+            NodeType::Program(nodes) => self.remove_in(nodes, true),
+            NodeType::Loop(nodes) => self.remove_in(nodes, false),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+
+
+
+
+/// Replaces loops like [>] and [<<], which only move the pointer until it hits a zero cell,
+/// with a ScanRight or ScanLeft of the same stride.
+pub fn collapse_scan_loop(node: &mut InstructionNode) {
+    match &mut node.node_type {
+        NodeType::Program(children) => collapse_nodes(children),
+        NodeType::Loop(children) => collapse_nodes(children),
+        _ => (),
+    }
+}
+fn collapse_nodes(nodes: &mut Vec<InstructionNode>) {
+    for node in nodes {
+        if let NodeType::Loop(children) = &node.node_type {
+            if children.len() == 1 {
+                let node_type = match children[0].node_type {
+                    NodeType::Next(stride) if stride != 0 => Some(NodeType::ScanRight(stride)),
+                    NodeType::Previous(stride) if stride != 0 => Some(NodeType::ScanLeft(stride)),
+                    _ => None,
+                };
+
+                if let Some(node_type) = node_type {
+                    node.node_type = node_type;
+                    continue;
+                }
+            }
+        }
+
+        collapse_scan_loop(node);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+
+
+
+
+/// Runs the start of the program at compile time, for as long as it doesn't depend on input or produce output,
+/// and replaces it with the SetAtOffset nodes and single Next reproducing the resulting tape and pointer.
+/// Folding stops at the first Input, Output or Debug, at a loop on a cell that isn't zero,
+/// and at anything that would move the pointer below the start of the tape or past MAX_FOLDED_CELLS.
+/// Cells are simulated with wrapping arithmetic on usize, like the values of SetCell,
+/// so the result is the same for every cell width the backends reduce it to.
+/// The values written out are kept modulo 2^32, the same as those folded into SetCell.
+/// For the same reason, only a cell that is zero at every width counts as zero when deciding whether a loop is skipped.
+pub fn fold_static_prefix(program: &mut InstructionNode) {
+    if let NodeType::Program(nodes) = &mut program.node_type {
+        fold_prefix(nodes);
+    }
+}
+
+
+fn fold_prefix(nodes: &mut Vec<InstructionNode>) {
+    let mut tape = StaticTape::new();
+    let mut folded = 0;
+
+    for node in nodes.iter() {
+        if !tape.apply(&node.node_type) {
+            break;
+        }
+        folded += 1;
+    }
+
+    if folded == 0 {
+        return;
+    }
+
+    let first = &nodes[0];
+    let last = &nodes[folded - 1];
+    let replacement = tape.into_nodes(first.line, first.char, first.byte_offset, last.byte_end);
+
+    // Folding an already folded prefix gives back the same nodes, so only ever replace with fewer.
+    if replacement.len() < folded {
+        nodes.splice(0..folded, replacement);
+    }
+}
+
+
+/// How many cells at the start of the tape are simulated.
+/// A prefix reaching further than this is mostly a long walk right, which isn't worth a tape of its size.
+const MAX_FOLDED_CELLS: usize = 4096;
+
+struct StaticTape {
+    cells: Vec<usize>,
+    p: usize,
+}
+impl StaticTape {
+    fn new() -> StaticTape {
+        StaticTape {
+            cells: Vec::new(),
+            p: 0,
+        }
+    }
+
+    fn cell(&self, index: usize) -> usize {
+        self.cells.get(index).copied().unwrap_or(0)
+    }
+    fn cell_mut(&mut self, index: usize) -> &mut usize {
+        if index >= self.cells.len() {
+            self.cells.resize(index + 1, 0);
+        }
+        &mut self.cells[index]
+    }
+    fn offset_index(&self, offset: isize) -> Option<usize> {
+        let index = (self.p as isize).checked_add(offset)?;
+        if index < 0 || index as usize >= MAX_FOLDED_CELLS {
+            None
+        }
+        else {
+            Some(index as usize)
+        }
+    }
+
+    /// Executes the instruction, unless it can't be run ahead of time, in which case nothing is changed.
+    fn apply(&mut self, node_type: &NodeType) -> bool {
+        match node_type {
+            NodeType::Next(amount) => {
+                match self.p.checked_add(*amount) {
+                    Some(p) if p < MAX_FOLDED_CELLS => self.p = p,
+                    _ => return false,
+                }
+            }
+            NodeType::Previous(amount) => {
+                if *amount > self.p {
+                    return false;
+                }
+                self.p -= amount;
+            }
+            NodeType::Increment(amount) => {
+                let cell = self.cell_mut(self.p);
+                *cell = cell.wrapping_add(*amount);
+            }
+            NodeType::Decrement(amount) => {
+                let cell = self.cell_mut(self.p);
+                *cell = cell.wrapping_sub(*amount);
+            }
+            NodeType::SetCell(value) => *self.cell_mut(self.p) = *value,
+            NodeType::AddAtOffset { offset, amount } => {
+                let target = match self.offset_index(*offset) {
+                    Some(target) => target,
+                    None => return false,
+                };
+                let cell = self.cell_mut(target);
+                *cell = cell.wrapping_add(*amount as isize as usize);
+            }
+            NodeType::SetAtOffset { offset, value } => {
+                let target = match self.offset_index(*offset) {
+                    Some(target) => target,
+                    None => return false,
+                };
+                *self.cell_mut(target) = *value;
+            }
+            NodeType::MoveAdd { offset } => {
+                let target = match self.offset_index(*offset) {
+                    Some(target) => target,
+                    None => return false,
+                };
+                let value = self.cell(self.p);
+                let cell = self.cell_mut(target);
+                *cell = cell.wrapping_add(value);
+            }
+            NodeType::MultiplyAdd { targets } => {
+                let value = self.cell(self.p);
+                if value == 0 {
+                    return true;
+                }
+                if targets.iter().any(|&(offset, _)| self.offset_index(offset).is_none()) {
+                    return false;
+                }
+
+                for &(offset, factor) in targets {
+                    let target = self.offset_index(offset).unwrap();
+                    let cell = self.cell_mut(target);
+                    *cell = cell.wrapping_add(value.wrapping_mul(factor as isize as usize));
+                }
+            }
+            NodeType::Loop(_) => {
+                if self.cell(self.p) != 0 {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn into_nodes(self, line: u32, char: u32, byte_offset: usize, byte_end: usize) -> Vec<InstructionNode> {
+        let node = |node_type| InstructionNode {
+            node_type,
+            line,
+            char,
+            byte_offset,
+            byte_end,
+        };
+
+        let mut nodes: Vec<InstructionNode> = self.cells.iter()
+            .enumerate()
+            .map(|(index, &value)| (index, value as u32 as usize))
+            .filter(|&(_, value)| value != 0)
+            .map(|(index, value)| node(NodeType::SetAtOffset {
+                offset: index as isize,
+                value,
+            }))
+            .collect();
+
+        if self.p != 0 {
+            nodes.push(node(NodeType::Next(self.p)));
+        }
+
+        nodes
+    }
+}
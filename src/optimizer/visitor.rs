@@ -0,0 +1,36 @@
+use crate::front_end::parser::{InstructionNode, NodeType};
+
+
+
+
+/// A traversal over the syntax tree, for passes that only care about some kinds of nodes.
+/// By default every node is visited, parents before their children,
+/// so implementations only override the methods they need and call walk_node or walk_children
+/// for whatever part of the tree they want to keep descending into.
+pub trait Visitor {
+    /// Called for every node in the tree.
+    fn visit_node(&mut self, node: &mut InstructionNode) {
+        walk_node(self, node);
+    }
+    /// Called with the children of every Program and Loop node,
+    /// so instructions can be merged, replaced or removed in sequence.
+    fn visit_children(&mut self, nodes: &mut Vec<InstructionNode>) {
+        walk_children(self, nodes);
+    }
+}
+
+/// Visits the children of a Program or Loop node. Does nothing for any other node.
+pub fn walk_node<V: Visitor + ?Sized>(visitor: &mut V, node: &mut InstructionNode) {
+    match &mut node.node_type {
+        NodeType::Program(nodes) => visitor.visit_children(nodes),
+        NodeType::Loop(nodes) => visitor.visit_children(nodes),
+        _ => (),
+    }
+}
+
+/// Visits each of the nodes in order.
+pub fn walk_children<V: Visitor + ?Sized>(visitor: &mut V, nodes: &mut [InstructionNode]) {
+    for node in nodes.iter_mut() {
+        visitor.visit_node(node);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::front_end::lexer::lex;
+use crate::front_end::parser::{parse, InstructionNode, ParsingError};
+use crate::optimizer::apply_default_optimizations;
+use crate::interpreter::{interpret, interpret_with_tape, Cell, SliceSource, VecWriter, InterpreterOptions, InterpretationError};
+use crate::compiler::{compile_to_executable, CompileOptions, EntryPoint, TargetOs};
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+
+
+
+/// Lexes, parses, optimizes and interprets the program with 8 bit cells and the default interpreter options,
+/// reading its input from the slice and returning everything it printed.
+pub fn run_source(src: &str, input: &[u8]) -> Result<Vec<u8>, RunError> {
+    let mut program = parse(&lex(src)).map_err(RunError::Parsing)?;
+    apply_default_optimizations(&mut program);
+
+    let mut out = VecWriter(Vec::new());
+    let mut source = SliceSource::new(input.to_vec());
+    interpret::<u8, _, _>(&program, &mut out, &mut source, &InterpreterOptions::default())
+        .map_err(RunError::Interpretation)?;
+
+    Ok(out.0)
+}
+
+/// Runs the program like run_source, with its input and output as text, which is what comparing a program's output
+/// against the output it is known to produce needs, like when checking that an optimization pass doesn't change it.
+/// Output that isn't valid UTF-8 gets replacement characters.
+/// Panics if the program fails to parse or to run, with the message of the error.
+pub fn run_to_string(src: &str, input: &str) -> String {
+    match run_source(src, input.as_bytes()) {
+        Ok(output) => String::from_utf8_lossy(&output).into_owned(),
+        Err(err) => panic!("Program failed: {}", err),
+    }
+}
+
+
+#[derive(Clone, Debug)]
+pub enum RunError {
+    /// The program didn't parse, with every unmatched bracket that was found.
+    Parsing(Vec<ParsingError>),
+    Interpretation(InterpretationError),
+}
+impl Display for RunError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parsing(errors) => {
+                let messages: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            Self::Interpretation(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+
+/// Interprets the program and runs it compiled, both on the same input, and compares what they printed.
+/// The compiled executable is written to the intermediate directory, named after the program with a verify_ prefix.
+/// Fails if the program can't be compiled or run, which also includes any target other than linux
+/// and the library entry point, neither of which make an executable that can be run here.
+pub fn verify_compiled<C: Cell>(program: &InstructionNode, options: &CompileOptions, interpreter_options: &InterpreterOptions, input: Vec<u8>) -> Result<Verification, String> {
+    if options.codegen.target_os != TargetOs::Linux {
+        return Err(String::from("--verify has to run the compiled program, so it only supports the linux target"));
+    }
+    if options.codegen.entry_point() == EntryPoint::Library {
+        return Err(String::from("--verify has to run the compiled program, which the library entry point doesn't make"));
+    }
+
+    let mut interpreted = VecWriter(Vec::new());
+    let mut source = SliceSource::new(input.clone());
+    let (_, result) = interpret_with_tape::<C, _, _>(program, &mut interpreted, &mut source, interpreter_options);
+
+    let mut options = options.clone();
+    let mut exe_path = options.int_dir.clone();
+    exe_path.push(format!("verify_{}", options.program_stem.to_string_lossy()));
+    options.output_path = Some(exe_path.clone());
+    compile_to_executable(program, &options).map_err(|err| err.to_string())?;
+
+    let compiled = run_executable(&exe_path, input)
+        .map_err(|err| format!("Failed to run compiled program {}: {}", exe_path.display(), err))?;
+
+    Ok(Verification {
+        interpreted: interpreted.0,
+        compiled,
+        interpreter_error: result.err(),
+    })
+}
+
+/// Runs the executable with the input on its stdin and returns what it wrote to stdout.
+fn run_executable(path: &Path, input: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    let mut child = Command::new(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    // Written from another thread, so a program printing lots of output before reading all of its input can't deadlock.
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || {
+        // The program may exit without reading everything, which is fine.
+        let _ = stdin.write_all(&input);
+    });
+
+    let output = child.wait_with_output()?;
+    let _ = writer.join();
+    Ok(output.stdout)
+}
+
+/// What the interpreter and the compiled program printed for the same input, as compared by verify_compiled.
+/// Displays as a description of whether and where they differ.
+#[derive(Clone, Debug)]
+pub struct Verification {
+    pub interpreted: Vec<u8>,
+    pub compiled: Vec<u8>,
+    /// The error the interpreter stopped with, in which case its output only goes up to that point.
+    pub interpreter_error: Option<InterpretationError>,
+}
+impl Verification {
+    pub fn outputs_match(&self) -> bool {
+        self.first_difference().is_none()
+    }
+    /// The offset of the first byte where the outputs differ, including one of them ending before the other.
+    pub fn first_difference(&self) -> Option<usize> {
+        let (a, b) = (&self.interpreted, &self.compiled);
+        let common = a.iter().zip(b).position(|(a, b)| a != b);
+        match common {
+            Some(offset) => Some(offset),
+            None if a.len() != b.len() => Some(a.len().min(b.len())),
+            None => None,
+        }
+    }
+}
+impl Display for Verification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.first_difference() {
+            None => write!(f, "Outputs match ({} bytes)", self.interpreted.len()),
+            Some(offset) => {
+                writeln!(
+                    f,
+                    "Outputs differ at byte {}: the interpreter wrote {}, the compiled program wrote {}",
+                    offset,
+                    describe_byte(self.interpreted.get(offset)),
+                    describe_byte(self.compiled.get(offset)),
+                )?;
+                write!(f, "The interpreter wrote {} bytes, the compiled program {}", self.interpreted.len(), self.compiled.len())
+            }
+        }
+    }
+}
+
+fn describe_byte(byte: Option<&u8>) -> String {
+    match byte {
+        Some(byte) => format!("{} ({:?})", byte, *byte as char),
+        None => String::from("nothing more"),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hello_world() {
+        let src = include_str!("../programs/hello_world.bf");
+        assert_eq!(run_to_string(src, ""), "Hello World!\n");
+    }
+
+    #[test]
+    fn cat() {
+        // The default EOF policy leaves the cell unchanged, so the input ends on a zero byte to stop the loop.
+        assert_eq!(run_to_string(",[.,]", "cat me\0"), "cat me");
+    }
+
+    #[test]
+    fn rot13() {
+        let src = "-,+[-[>>++++[>++++++++<-]<+<-[>+>+>-[>>>]<[[>+<-]>>+>]<<<<<-]]>>>[-]+>--[-[<->+++[-]]]\
+            <[++++++++++++<[>-[>+>>]>[+[<+>-]>+>>]<<<<<-]>>[<+>-]>[-[-<<[-]>>]<<[<<->>-]>>]<<[<<+>>-]]<[-]<.[-]<-,+]";
+        assert_eq!(run_to_string(src, "Hello, World!\n"), "Uryyb, Jbeyq!\n");
+    }
+
+    #[test]
+    fn mandelbrot_subset() {
+        let src = include_str!("../programs/mandelbrot_small.bf");
+        let expected = concat!(
+            "                *    \n",
+            "             ** **   \n",
+            "           ******* * \n",
+            "          ********** \n",
+            "           ********* \n",
+            "       ************* \n",
+            "     **************  \n",
+            " ******************  \n",
+            " ******************  \n",
+            " ******************  \n",
+            "     **************  \n",
+            "       ************* \n",
+            "           ********* \n",
+            "          ********** \n",
+            "           ******* * \n",
+            "             ** **   \n",
+            "                *    \n",
+        );
+        assert_eq!(run_to_string(src, ""), expected);
+    }
+
+    #[test]
+    fn verification_finds_first_difference() {
+        let verification = |interpreted: &[u8], compiled: &[u8]| Verification {
+            interpreted: interpreted.to_vec(),
+            compiled: compiled.to_vec(),
+            interpreter_error: None,
+        };
+
+        assert_eq!(verification(b"abc", b"abc").first_difference(), None);
+        assert_eq!(verification(b"abc", b"abd").first_difference(), Some(2));
+        assert_eq!(verification(b"abc", b"ab").first_difference(), Some(2));
+        assert_eq!(verification(b"", b"a").first_difference(), Some(0));
+    }
+}
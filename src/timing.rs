@@ -0,0 +1,57 @@
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+
+
+
+/// How long each phase of handling a program took, in the order they ran,
+/// along with counts like the number of tokens and nodes to put the times in context.
+#[derive(Clone, Debug, Default)]
+pub struct PhaseTimes {
+    pub phases: Vec<(&'static str, Duration)>,
+    pub counts: Vec<(&'static str, usize)>,
+}
+impl PhaseTimes {
+    pub fn new() -> PhaseTimes {
+        PhaseTimes::default()
+    }
+
+    /// Runs the phase, recording how long it took under the name.
+    pub fn time<T, F: FnOnce() -> T>(&mut self, name: &'static str, phase: F) -> T {
+        let start = Instant::now();
+        let result = phase();
+        self.phases.push((name, start.elapsed()));
+        result
+    }
+    pub fn count(&mut self, name: &'static str, count: usize) {
+        self.counts.push((name, count));
+    }
+
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|&(_, duration)| duration).sum()
+    }
+}
+impl Display for PhaseTimes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let width = self.phases.iter()
+            .map(|(name, _)| name.len())
+            .chain(std::iter::once("total".len()))
+            .max()
+            .unwrap();
+
+        for (name, duration) in &self.phases {
+            writeln!(f, "  {:<width$}  {:>10.3} ms", name, millis(*duration), width = width)?;
+        }
+        write!(f, "  {:<width$}  {:>10.3} ms", "total", millis(self.total()), width = width)?;
+
+        for (name, count) in &self.counts {
+            write!(f, "\n  {}: {}", name, count)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}